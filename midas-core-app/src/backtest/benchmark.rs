@@ -0,0 +1,175 @@
+use rust_decimal::{Decimal, MathematicalOps};
+use serde::{Deserialize, Serialize};
+
+use super::equity_curve::IEquityPoint;
+use super::metrics::{mean_and_deviation, IMetricsParams};
+use crate::error::MidasError;
+use crate::get_price::candle::PriceSeries;
+
+/// Strategy performance relative to a benchmark, annualized with `IMetricsParams`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IBenchmarkMetrics {
+    /// Compounded return of the benchmark over the backtest
+    pub benchmark_return: Decimal,
+    /// Strategy return minus benchmark return
+    pub excess_return: Decimal,
+    /// Annualized Jensen's alpha
+    pub alpha: Decimal,
+    pub beta: Decimal,
+    /// Annualized deviation of the strategy returns from the benchmark returns
+    pub tracking_error: Decimal,
+    /// Annualized mean active return over the tracking error
+    pub information_ratio: Decimal,
+}
+
+/// Per bar returns of holding from the first close, the first return is zero
+/// A bar after a zero close has a zero return, there is nothing to hold
+pub fn get_buy_and_hold_returns(closes: &[Decimal]) -> Vec<Decimal> {
+    let mut returns: Vec<Decimal> = Vec::with_capacity(closes.len());
+    for index in 0..closes.len() {
+        if index == 0 {
+            returns.push(Decimal::ZERO);
+            continue;
+        }
+        returns.push(
+            (closes[index] - closes[index - 1])
+                .checked_div(closes[index - 1])
+                .unwrap_or(Decimal::ZERO),
+        );
+    }
+    returns
+}
+
+/// Per bar returns of holding `benchmark`, aligned on the timestamps of `curve`
+/// Every bar uses the last benchmark close at or before its timestamp
+pub fn get_aligned_benchmark_returns(
+    benchmark: &PriceSeries,
+    curve: &[IEquityPoint<Decimal>],
+) -> Result<Vec<Decimal>, MidasError> {
+    let candles = benchmark.candles();
+    let mut closes: Vec<Decimal> = Vec::with_capacity(curve.len());
+    for point in curve {
+        let end = candles.partition_point(|candle| candle.timestamp <= point.timestamp);
+        if end == 0 {
+            return Err(MidasError::InvalidInput(format!(
+                "Benchmark starts after bar {} at {}",
+                point.index, point.timestamp
+            )));
+        }
+        closes.push(candles[end - 1].close);
+    }
+    Ok(get_buy_and_hold_returns(&closes))
+}
+
+/// Compare per bar strategy returns with benchmark returns of the same bars
+pub fn get_benchmark_metrics(
+    returns: &[Decimal],
+    benchmark: &[Decimal],
+    params: &IMetricsParams,
+) -> Result<IBenchmarkMetrics, MidasError> {
+    if returns.len() != benchmark.len() {
+        return Err(MidasError::InvalidInput(format!(
+            "{} strategy returns against {} benchmark returns",
+            returns.len(),
+            benchmark.len()
+        )));
+    }
+    if returns.is_empty() {
+        return Ok(IBenchmarkMetrics::default());
+    }
+    let compound = |values: &[Decimal]| -> Decimal {
+        values
+            .iter()
+            .fold(Decimal::ONE, |value, r| value * (Decimal::ONE + r))
+            - Decimal::ONE
+    };
+    let periods = Decimal::from(params.periods_per_year);
+    let risk_free = params.risk_free_per_period();
+
+    let (mean, _) = mean_and_deviation(returns)?;
+    let (benchmark_mean, benchmark_deviation) = mean_and_deviation(benchmark)?;
+    let covariance = returns
+        .iter()
+        .zip(benchmark)
+        .map(|(r, b)| (r - mean) * (b - benchmark_mean))
+        .sum::<Decimal>()
+        / Decimal::from(returns.len());
+    let beta = covariance
+        .checked_div(benchmark_deviation * benchmark_deviation)
+        .unwrap_or(Decimal::ZERO);
+
+    let active: Vec<Decimal> = returns.iter().zip(benchmark).map(|(r, b)| r - b).collect();
+    let (active_mean, active_deviation) = mean_and_deviation(&active)?;
+    let tracking_error = active_deviation * periods.sqrt().unwrap_or(Decimal::ONE);
+
+    let benchmark_return = compound(benchmark);
+    Ok(IBenchmarkMetrics {
+        benchmark_return,
+        excess_return: compound(returns) - benchmark_return,
+        alpha: (mean - risk_free - beta * (benchmark_mean - risk_free)) * periods,
+        beta,
+        tracking_error,
+        information_ratio: (active_mean * periods)
+            .checked_div(tracking_error)
+            .unwrap_or(Decimal::ZERO),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_price::candle::Candle;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_benchmark_metrics() {
+        let params = IMetricsParams {
+            risk_free_rate: dec!(0),
+            periods_per_year: 4,
+        };
+        let benchmark = [dec!(0), dec!(0.1), dec!(-0.1), dec!(0.2)];
+        // * Twice the benchmark plus 1% a bar
+        let returns: Vec<Decimal> = benchmark.iter().map(|b| b * dec!(2) + dec!(0.01)).collect();
+
+        let metrics = get_benchmark_metrics(&returns, &benchmark, &params).unwrap();
+
+        assert!(metrics.beta == dec!(2));
+        assert!(metrics.alpha.round_dp(10) == dec!(0.04));
+        assert!(metrics.benchmark_return == dec!(0.188));
+        assert!(metrics.tracking_error > Decimal::ZERO);
+        assert!(metrics.information_ratio > Decimal::ZERO);
+        assert!(get_benchmark_metrics(&returns[1..], &benchmark, &params).is_err());
+    }
+
+    #[test]
+    fn test_aligned_benchmark_returns() {
+        let benchmark = PriceSeries::new(vec![
+            Candle::from_price(0, dec!(100), dec!(1)),
+            Candle::from_price(20, dec!(110), dec!(1)),
+        ]);
+        let curve: Vec<IEquityPoint<Decimal>> = [5, 10, 25]
+            .iter()
+            .enumerate()
+            .map(|(index, timestamp)| IEquityPoint {
+                timestamp: *timestamp,
+                index,
+                cash: dec!(1),
+                quantity: dec!(0),
+                position_value: dec!(0),
+                equity: dec!(1),
+                drawdown: dec!(0),
+            })
+            .collect();
+
+        let returns = get_aligned_benchmark_returns(&benchmark, &curve).unwrap();
+
+        assert!(returns == vec![dec!(0), dec!(0), dec!(0.1)]);
+        assert!(
+            get_buy_and_hold_returns(&[dec!(1), dec!(0), dec!(2), dec!(3)])
+                == vec![dec!(0), dec!(-1), dec!(0), dec!(0.5)]
+        );
+        assert!(get_aligned_benchmark_returns(&benchmark, &curve[..0])
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::get_price::candle::Candle;
+
+const BASIS_POINT: Decimal = dec!(10000);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderType {
+    Maker,
+    Taker,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Slippage {
+    None,
+    /// Constant slippage in basis points of price
+    Bps(Decimal),
+    /// Slippage as a fraction of price, from the fill bar and the order notional
+    Custom(fn(candle: &Candle, notional: Decimal) -> Decimal),
+}
+
+/// Costs paid on every fill
+#[derive(Debug, Clone, Copy)]
+pub struct ICostModel {
+    pub maker_fee_bps: Decimal,
+    pub taker_fee_bps: Decimal,
+    /// Flat fee per fill, in quote currency
+    pub fixed_fee: Decimal,
+    /// Full bid / ask spread as a fraction of price, half of it is paid on each fill
+    pub spread: Decimal,
+    pub slippage: Slippage,
+    /// Which of maker or taker fee applies to the fills
+    pub order_type: OrderType,
+}
+
+impl ICostModel {
+    pub fn new() -> ICostModel {
+        ICostModel {
+            maker_fee_bps: Decimal::ZERO,
+            taker_fee_bps: Decimal::ZERO,
+            fixed_fee: Decimal::ZERO,
+            spread: Decimal::ZERO,
+            slippage: Slippage::None,
+            order_type: OrderType::Taker,
+        }
+    }
+    /// Fee as a fraction of notional
+    pub fn fee_rate(&self) -> Decimal {
+        match self.order_type {
+            OrderType::Maker => self.maker_fee_bps / BASIS_POINT,
+            OrderType::Taker => self.taker_fee_bps / BASIS_POINT,
+        }
+    }
+    pub fn fee(&self, notional: Decimal) -> Decimal {
+        notional.abs() * self.fee_rate() + self.fixed_fee
+    }
+    /// Price actually paid (`is_buy`) or received after half spread and slippage
+    pub fn fill_price(
+        &self,
+        price: Decimal,
+        is_buy: bool,
+        candle: &Candle,
+        notional: Decimal,
+    ) -> Decimal {
+        let slippage: Decimal = match self.slippage {
+            Slippage::None => Decimal::ZERO,
+            Slippage::Bps(bps) => bps / BASIS_POINT,
+            Slippage::Custom(slippage) => slippage(candle, notional),
+        };
+        let cost: Decimal = self.spread / dec!(2) + slippage;
+
+        if is_buy {
+            price * (Decimal::ONE + cost)
+        } else {
+            price * (Decimal::ONE - cost)
+        }
+    }
+}
+
+impl Default for ICostModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::mock::mock_candles;
+
+    fn volume_slippage(candle: &Candle, notional: Decimal) -> Decimal {
+        notional / (candle.volume * candle.close) / dec!(100)
+    }
+
+    #[test]
+    fn test_cost_model() {
+        let candle = mock_candles()[0];
+        let costs = ICostModel {
+            maker_fee_bps: dec!(2),
+            taker_fee_bps: dec!(10),
+            fixed_fee: dec!(1),
+            spread: dec!(0.002),
+            slippage: Slippage::Bps(dec!(5)),
+            order_type: OrderType::Taker,
+        };
+
+        assert!(costs.fee(dec!(1000)) == dec!(2));
+        assert!(costs.fill_price(dec!(100), true, &candle, dec!(1000)) == dec!(100.15));
+        assert!(costs.fill_price(dec!(100), false, &candle, dec!(1000)) == dec!(99.85));
+
+        let costs = ICostModel {
+            order_type: OrderType::Maker,
+            slippage: Slippage::Custom(volume_slippage),
+            spread: Decimal::ZERO,
+            ..costs
+        };
+        assert!(costs.fee(dec!(1000)) == dec!(1.2));
+        // * Mock candle has volume 1000 at price 2
+        assert!(costs.fill_price(dec!(2), true, &candle, dec!(200)) == dec!(2.002));
+    }
+}
@@ -0,0 +1,629 @@
+use rust_decimal::Decimal;
+
+use super::benchmark::get_buy_and_hold_returns;
+use super::execution::EndOfData;
+use super::strategy::{find_look_ahead, Signal, Strategy};
+use super::{
+    IBackTestingParams, IBackTestingResult, IBackTestingSingleResult, IEquityPoint, IOpenPosition,
+    POSITION,
+};
+use crate::error::MidasError;
+use crate::get_price::candle::{Candle, PriceSeries};
+use crate::risk_management::exit_levels::{ExitReason, IExitLevels};
+
+/// Portfolio state while replaying fills
+struct Simulation<'a, 'b> {
+    params: &'a IBackTestingParams<'b>,
+    cash: Decimal,
+    open_position: Option<IBackTestingSingleResult<Decimal>>,
+    exit_levels: Option<IExitLevels>,
+    /// Cash before the open position was entered
+    cash_before_entry: Decimal,
+    result: Vec<IBackTestingSingleResult<Decimal>>,
+    /// Highest equity so far, starting at the initial capital
+    peak: Decimal,
+    equity_curve: Vec<IEquityPoint<Decimal>>,
+}
+
+impl<'a, 'b> Simulation<'a, 'b> {
+    fn new(params: &'a IBackTestingParams<'b>) -> Simulation<'a, 'b> {
+        Simulation {
+            params,
+            cash: params.initial_capital,
+            open_position: None,
+            exit_levels: None,
+            cash_before_entry: params.initial_capital,
+            result: Vec::new(),
+            peak: params.initial_capital,
+            equity_curve: Vec::new(),
+        }
+    }
+    /// Signed quantity held, negative for a short
+    fn quantity(&self) -> Decimal {
+        match self.open_position {
+            Some(entry) if entry.action == POSITION::Short => -entry.quantity,
+            Some(entry) => entry.quantity,
+            None => Decimal::ZERO,
+        }
+    }
+    /// Open a position at `price` of `candle`, sized by `params.sizing`
+    /// `closes` holds every close known when the signal was given
+    fn open(
+        &mut self,
+        action: POSITION,
+        candle: &Candle,
+        index: usize,
+        price: Decimal,
+        closes: &[Decimal],
+    ) -> Result<(), MidasError> {
+        let costs = &self.params.costs;
+        let is_long = action == POSITION::Long;
+        let fill_price = costs.fill_price(price, is_long, candle, self.cash);
+        let exit_levels = IExitLevels::new(&self.params.exits, price, is_long);
+        let quantity = self.params.sizing.quantity(
+            self.cash,
+            fill_price,
+            exit_levels.stop_loss,
+            closes,
+            costs,
+        )?;
+        if quantity <= Decimal::ZERO {
+            return Ok(());
+        }
+        let fee = costs.fee(quantity * fill_price);
+        self.cash_before_entry = self.cash;
+        if is_long {
+            self.cash -= quantity * fill_price + fee;
+        } else {
+            self.cash += quantity * fill_price - fee;
+        }
+        let fill = IBackTestingSingleResult {
+            action,
+            price,
+            timestamp: candle.timestamp,
+            index,
+            fill_price,
+            quantity,
+            fee,
+            exit_reason: None,
+        };
+        self.open_position = Some(fill);
+        self.exit_levels = Some(exit_levels);
+        self.result.push(fill);
+        Ok(())
+    }
+    /// Close the open position at `price` before costs
+    fn close(&mut self, candle: &Candle, index: usize, price: Decimal, reason: ExitReason) {
+        let Some(entry) = self.open_position.take() else {
+            return;
+        };
+        self.exit_levels = None;
+        let costs = &self.params.costs;
+        let is_long = entry.action == POSITION::Long;
+        let fill_price = costs.fill_price(price, !is_long, candle, entry.quantity * price);
+        let fee = costs.fee(entry.quantity * fill_price);
+        if is_long {
+            self.cash += entry.quantity * fill_price - fee;
+        } else {
+            self.cash -= entry.quantity * fill_price + fee;
+        }
+        self.result.push(IBackTestingSingleResult {
+            action: if is_long {
+                POSITION::Close
+            } else {
+                POSITION::Cover
+            },
+            price,
+            timestamp: candle.timestamp,
+            index,
+            fill_price,
+            quantity: entry.quantity,
+            fee,
+            exit_reason: Some(reason),
+        });
+    }
+    /// Act on `signal` at `price` of `candle`, only closing positions unless `can_open`
+    fn apply(
+        &mut self,
+        signal: Option<Signal>,
+        candle: &Candle,
+        index: usize,
+        price: Decimal,
+        closes: &[Decimal],
+        can_open: bool,
+    ) -> Result<(), MidasError> {
+        let allow_short = self.params.allow_short && can_open;
+        let open_position = self.open_position.map(|position| position.action);
+        match (signal, open_position) {
+            (Some(Signal::Long), None) if can_open => {
+                self.open(POSITION::Long, candle, index, price, closes)?
+            }
+            (Some(Signal::Long), Some(POSITION::Short)) => {
+                self.close(candle, index, price, ExitReason::Signal);
+                if can_open {
+                    self.open(POSITION::Long, candle, index, price, closes)?
+                }
+            }
+            (Some(Signal::Short), None) if allow_short => {
+                self.open(POSITION::Short, candle, index, price, closes)?
+            }
+            (Some(Signal::Short), Some(POSITION::Long)) => {
+                self.close(candle, index, price, ExitReason::Signal);
+                if allow_short {
+                    self.open(POSITION::Short, candle, index, price, closes)?
+                }
+            }
+            (Some(Signal::Close), Some(_)) => self.close(candle, index, price, ExitReason::Signal),
+            _ => (),
+        }
+        Ok(())
+    }
+    /// Close on a stop or target crossed by `candle`, otherwise trail the stop with it
+    fn check_exits(&mut self, candle: &Candle, index: usize) {
+        let Some(levels) = self.exit_levels.as_mut() else {
+            return;
+        };
+        match levels.check(candle.open, candle.high, candle.low) {
+            Some((price, reason)) => self.close(candle, index, price, reason),
+            None => levels.update(candle.high, candle.low),
+        }
+    }
+    /// Forget the open position and keep its capital in cash from its entry bar on
+    fn exclude_open_position(&mut self) -> Result<(), MidasError> {
+        let Some(entry) = self.open_position.take() else {
+            return Ok(());
+        };
+        self.exit_levels = None;
+        self.result.pop();
+        self.cash = self.cash_before_entry;
+        let mut peak = self.params.initial_capital;
+        for point in self.equity_curve.iter_mut() {
+            if point.index >= entry.index {
+                *point = IEquityPoint::new(
+                    point.timestamp,
+                    point.index,
+                    self.cash,
+                    Decimal::ZERO,
+                    Decimal::ZERO,
+                    peak,
+                )?;
+            }
+            peak = peak.max(point.equity);
+        }
+        Ok(())
+    }
+    /// Mark the portfolio to the close of `candle`
+    fn record(&mut self, candle: &Candle, index: usize) -> Result<(), MidasError> {
+        let point = IEquityPoint::new(
+            candle.timestamp,
+            index,
+            self.cash,
+            self.quantity(),
+            candle.close,
+            self.peak,
+        )?;
+        self.peak = self.peak.max(point.equity);
+        self.equity_curve.push(point);
+        Ok(())
+    }
+}
+
+/// Turn signals into positions, filled with the timing, costs and sizing of `params`
+/// Stops and targets of `params.exits` are checked first on every bar after the entry
+/// Signals of the last bars are dropped when `params.fill` waits for a bar that does not exist
+/// A position still open on the last bar is handled by `params.end_of_data`, `ForceClose` opens
+/// nothing on that bar
+/// * `Long` opens a long, covering an open short first
+/// * `Short` closes an open long, then opens a short when `allow_short`
+/// * `Close` closes whatever is open
+pub fn get_positions_from_signals(
+    signals: &[Option<Signal>],
+    series: &PriceSeries,
+    params: &IBackTestingParams,
+) -> Result<IBackTestingResult<Decimal>, MidasError> {
+    let candles = series.candles();
+    if signals.len() > candles.len() {
+        return Err(MidasError::InsufficientData {
+            required: signals.len(),
+            available: candles.len(),
+        });
+    }
+
+    let closes = series.closes();
+    let delay = params.fill.delay();
+    let mut simulation = Simulation::new(params);
+    for (index, candle) in candles[..signals.len()].iter().enumerate() {
+        let force_close = index + 1 == signals.len() && params.end_of_data == EndOfData::ForceClose;
+        simulation.check_exits(candle, index);
+        // * Fill the signal given `delay` bars ago, sized on the closes known at that time
+        // * A position opened on the bar it is force closed on would be an empty trade
+        if let Some(signal_index) = index.checked_sub(delay) {
+            simulation.apply(
+                signals[signal_index],
+                candle,
+                index,
+                params.fill.price(candle),
+                &closes[..=signal_index],
+                !force_close,
+            )?;
+        }
+        if force_close {
+            simulation.close(candle, index, candle.close, ExitReason::EndOfData);
+        }
+        simulation.record(candle, index)?;
+    }
+
+    let open_position = match (params.end_of_data, simulation.open_position) {
+        (EndOfData::Exclude, _) => {
+            simulation.exclude_open_position()?;
+            None
+        }
+        (_, Some(entry)) => Some(IOpenPosition::new(entry, closes[signals.len() - 1])),
+        (_, None) => None,
+    };
+    Ok(IBackTestingResult {
+        result: simulation.result,
+        equity_curve: simulation.equity_curve,
+        initial_capital: params.initial_capital,
+        benchmark: get_buy_and_hold_returns(&closes[..signals.len()]),
+        open_position,
+    })
+}
+
+/// Backtest the signals of `strategy` on `series`
+pub fn run_strategy(
+    strategy: &mut dyn Strategy,
+    series: &PriceSeries,
+    params: &IBackTestingParams,
+) -> Result<IBackTestingResult<Decimal>, MidasError> {
+    if params.check_look_ahead {
+        if let Some(index) = find_look_ahead(strategy, series)? {
+            return Err(MidasError::InvalidInput(format!(
+                "Strategy {} reads bars after bar {index}",
+                strategy.name()
+            )));
+        }
+    }
+    let signals = strategy.signals(series)?;
+    get_positions_from_signals(&signals, series, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::costs::ICostModel;
+    use crate::backtest::execution::FillTiming;
+    use crate::backtest::sizing::PositionSizing;
+    use crate::backtest::strategy::MaCrossStrategy;
+    use crate::backtest::summary::IBackTestingSummary;
+    use crate::risk_management::exit_levels::{IExitRules, StopLevel};
+    use crate::utils::mock::{mock_candles, MockHindsight};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_get_positions_from_signals() {
+        let series = PriceSeries::new(mock_candles());
+        let signals = [
+            Some(Signal::Short),
+            Some(Signal::Long),
+            Some(Signal::Long),
+            None,
+            Some(Signal::Close),
+            Some(Signal::Close),
+            Some(Signal::Long),
+        ];
+
+        let params = IBackTestingParams::new("mock", 30);
+        let positions = get_positions_from_signals(&signals, &series, &params).unwrap();
+        let actions: Vec<(POSITION, usize)> = positions
+            .result
+            .iter()
+            .map(|position| (position.action, position.index))
+            .collect();
+
+        assert!(
+            actions
+                == vec![
+                    (POSITION::Long, 1),
+                    (POSITION::Close, 4),
+                    (POSITION::Long, 6),
+                ]
+        );
+        assert!(positions.result[1].price == series.candles()[4].close);
+    }
+
+    #[test]
+    fn test_get_positions_from_signals_with_short() {
+        let series = PriceSeries::new(mock_candles());
+        // * Start at 21.4 where prices are choppy, a short from 2 to 4.32 would wipe the capital
+        let mut signals = vec![None; 9];
+        signals.extend([
+            Some(Signal::Short),
+            Some(Signal::Long),
+            Some(Signal::Short),
+            Some(Signal::Close),
+            Some(Signal::Close),
+        ]);
+
+        let params = IBackTestingParams {
+            allow_short: true,
+            ..IBackTestingParams::new("mock", 30)
+        };
+        let positions = get_positions_from_signals(&signals, &series, &params).unwrap();
+        let actions: Vec<(POSITION, usize)> = positions
+            .result
+            .iter()
+            .map(|position| (position.action, position.index))
+            .collect();
+
+        assert!(
+            actions
+                == vec![
+                    (POSITION::Short, 9),
+                    (POSITION::Cover, 10),
+                    (POSITION::Long, 10),
+                    (POSITION::Close, 11),
+                    (POSITION::Short, 11),
+                    (POSITION::Cover, 12),
+                ]
+        );
+    }
+
+    #[test]
+    fn test_get_positions_from_signals_with_costs() {
+        let series = PriceSeries::new(mock_candles());
+        let signals = [Some(Signal::Long), None, Some(Signal::Close)];
+        let params = IBackTestingParams {
+            initial_capital: dec!(1001),
+            costs: ICostModel {
+                taker_fee_bps: dec!(10),
+                spread: dec!(0.02),
+                ..ICostModel::new()
+            },
+            ..IBackTestingParams::new("mock", 30)
+        };
+
+        let result = get_positions_from_signals(&signals, &series, &params).unwrap();
+        let entry = result.result[0];
+        let close = result.result[1];
+
+        // * Buy at 2 * 1.01, 1001 / (2.02 * 1.001) = 495.04...
+        assert!(entry.fill_price == dec!(2.02));
+        assert!(entry.quantity * entry.fill_price + entry.fee == dec!(1001));
+        assert!(close.fill_price == dec!(6.56) * dec!(0.99));
+        assert!(close.quantity == entry.quantity);
+
+        let trade = &result.get_entry_and_close().unwrap()[0];
+        assert!(trade.gross_returns == dec!(2.28));
+        assert!(trade.returns < trade.gross_returns);
+        assert!(trade.fees == entry.fee + close.fee);
+    }
+
+    #[test]
+    fn test_get_positions_from_signals_with_exits() {
+        let series = PriceSeries::new(mock_candles());
+        let exits = |exits: IExitRules| -> Vec<(POSITION, usize, Decimal, Option<ExitReason>)> {
+            let mut signals = vec![None; series.len()];
+            signals[0] = Some(Signal::Long);
+            signals[9] = Some(Signal::Long);
+            let params = IBackTestingParams {
+                exits,
+                ..IBackTestingParams::new("mock", 30)
+            };
+            get_positions_from_signals(&signals, &series, &params)
+                .unwrap()
+                .result
+                .iter()
+                .map(|fill| (fill.action, fill.index, fill.price, fill.exit_reason))
+                .collect()
+        };
+
+        // * Mock candles are flat, so a crossed level always fills at the open
+        let take_profit = exits(IExitRules {
+            take_profit: Some(StopLevel::Fixed(dec!(10))),
+            ..IExitRules::default()
+        });
+        assert!(take_profit[1] == (POSITION::Close, 4, dec!(12.9), Some(ExitReason::TakeProfit)));
+
+        // * Highest close is 21.4 at bar 9, the trailing stop at 19.26 is crossed by 18.22
+        let trailing = exits(IExitRules {
+            trailing_stop: Some(StopLevel::Percent(dec!(0.1))),
+            ..IExitRules::default()
+        });
+        assert!(trailing.len() == 2);
+        assert!(
+            trailing[1]
+                == (
+                    POSITION::Close,
+                    15,
+                    dec!(18.22),
+                    Some(ExitReason::TrailingStop)
+                )
+        );
+
+        let stop_loss = exits(IExitRules {
+            stop_loss: Some(StopLevel::Percent(dec!(0.5))),
+            take_profit: Some(StopLevel::Percent(dec!(0.5))),
+            ..IExitRules::default()
+        });
+        // * 2 -> 3.0 target at bar 1, then 21.4 -> 10.7 stop is never crossed
+        assert!(stop_loss[1] == (POSITION::Close, 1, dec!(4.32), Some(ExitReason::TakeProfit)));
+        assert!(stop_loss.len() == 3);
+    }
+
+    #[test]
+    fn test_get_positions_from_signals_with_fill_timing() {
+        // * Every bar opens 1 above the previous close and trades 2 around its close
+        let series = PriceSeries::new(
+            mock_candles()
+                .iter()
+                .map(|candle| Candle {
+                    open: candle.close - dec!(1),
+                    high: candle.close + dec!(2),
+                    low: candle.close - dec!(2),
+                    ..*candle
+                })
+                .collect(),
+        );
+        let signals = [
+            Some(Signal::Long),
+            None,
+            Some(Signal::Close),
+            Some(Signal::Long),
+        ];
+        let fills = |fill: FillTiming| -> Vec<(POSITION, usize, Decimal)> {
+            let params = IBackTestingParams {
+                fill,
+                ..IBackTestingParams::new("mock", 30)
+            };
+            get_positions_from_signals(&signals, &series, &params)
+                .unwrap()
+                .result
+                .iter()
+                .map(|fill| (fill.action, fill.index, fill.price))
+                .collect()
+        };
+
+        assert!(fills(FillTiming::SameBarClose)[0] == (POSITION::Long, 0, dec!(2)));
+        // * The long of the last bar has no next bar to fill on
+        assert!(
+            fills(FillTiming::NextBarOpen)
+                == vec![
+                    (POSITION::Long, 1, dec!(3.32)),
+                    (POSITION::Close, 3, dec!(7.1)),
+                ]
+        );
+        assert!(fills(FillTiming::NextBarClose)[1] == (POSITION::Close, 3, dec!(8.1)));
+        assert!(fills(FillTiming::NextBarVwap)[0] == (POSITION::Long, 1, dec!(4.32)));
+    }
+
+    #[test]
+    fn test_run_strategy_look_ahead_guard() {
+        let series = PriceSeries::new(mock_candles());
+        let params = IBackTestingParams {
+            check_look_ahead: true,
+            ..IBackTestingParams::new("mock", 30)
+        };
+
+        assert!(run_strategy(&mut MockHindsight, &series, &params).is_err());
+        assert!(run_strategy(&mut MaCrossStrategy::default(), &series, &params).is_ok());
+    }
+
+    #[test]
+    fn test_get_positions_from_signals_end_of_data() {
+        let series = PriceSeries::new(mock_candles());
+        let mut signals = vec![None; series.len()];
+        signals[9] = Some(Signal::Long);
+        let run = |end_of_data: EndOfData| {
+            let params = IBackTestingParams {
+                end_of_data,
+                ..IBackTestingParams::new("mock", 30)
+            };
+            let result = get_positions_from_signals(&signals, &series, &params).unwrap();
+            let summary = IBackTestingSummary::calculate(&result).unwrap();
+            (result, summary)
+        };
+        // * Bought at 21.4, the last close is 24.12
+        let gain = dec!(24.12) / dec!(21.4) - Decimal::ONE;
+
+        let (result, summary) = run(EndOfData::ForceClose);
+        assert!(result.result[1].exit_reason == Some(ExitReason::EndOfData));
+        assert!(result.result[1].index == 29);
+        assert!(summary.total_trade == 1);
+        assert!(summary.open_position.is_none());
+
+        // * A long on the last bar is not opened, a reversal there only closes
+        let params = IBackTestingParams {
+            end_of_data: EndOfData::ForceClose,
+            allow_short: true,
+            ..IBackTestingParams::new("mock", 30)
+        };
+        let mut last = vec![None; series.len()];
+        last[29] = Some(Signal::Long);
+        let result = get_positions_from_signals(&last, &series, &params).unwrap();
+        assert!(result.result.is_empty());
+        let mut last = signals.clone();
+        last[29] = Some(Signal::Short);
+        let result = get_positions_from_signals(&last, &series, &params).unwrap();
+        assert!(result.result.len() == 2);
+        assert!(result.result[1].action == POSITION::Close);
+        assert!(result.result[1].exit_reason == Some(ExitReason::Signal));
+
+        let (result, summary) = run(EndOfData::ReportOpen);
+        let open = result.open_position.unwrap();
+        assert!(result.result.len() == 1);
+        assert!(summary.total_trade == 0);
+        assert!(open.mark_price == dec!(24.12));
+        assert!(open.unrealized_return == gain);
+        assert!(open.market_value == result.equity_curve[29].position_value);
+        assert!(summary.open_position.is_some());
+        assert!(summary.returns.round_dp(10) == gain.round_dp(10));
+
+        let (result, summary) = run(EndOfData::Exclude);
+        assert!(result.result.is_empty());
+        assert!(result.open_position.is_none());
+        assert!(result
+            .equity_curve
+            .iter()
+            .all(|point| point.equity == dec!(10000) && point.quantity.is_zero()));
+        assert!(summary.returns == Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_get_positions_from_signals_with_sizing() {
+        let series = PriceSeries::new(mock_candles());
+        let signals = [Some(Signal::Long), None, Some(Signal::Close)];
+        let params = IBackTestingParams {
+            sizing: PositionSizing::FixedFraction(dec!(0.5)),
+            ..IBackTestingParams::new("mock", 30)
+        };
+
+        let result = get_positions_from_signals(&signals, &series, &params).unwrap();
+        let states: Vec<(Decimal, Decimal, Decimal)> = result
+            .equity_curve
+            .iter()
+            .map(|point| (point.cash, point.quantity, point.equity))
+            .collect();
+
+        // * Half of 10000 buys 2500 at 2, sold at 6.56
+        assert!(
+            states
+                == vec![
+                    (dec!(5000), dec!(2500), dec!(10000)),
+                    (dec!(5000), dec!(2500), dec!(15800)),
+                    (dec!(21400), dec!(0), dec!(21400)),
+                ]
+        );
+        let summary = futures::executor::block_on(result.get_summary()).unwrap();
+        assert!(summary.returns == dec!(1.14));
+    }
+
+    #[test]
+    fn test_equity_curve_drawdown_inside_trade() {
+        let series = PriceSeries::new(mock_candles());
+        let mut signals = vec![None; 22];
+        signals[9] = Some(Signal::Long);
+        signals[21] = Some(Signal::Close);
+
+        let params = IBackTestingParams::new("mock", 30);
+        let result = get_positions_from_signals(&signals, &series, &params).unwrap();
+        let summary = futures::executor::block_on(result.get_summary()).unwrap();
+
+        assert!(result.equity_curve.len() == 22);
+        assert!(result.equity_curve[9].position_value == dec!(10000));
+        // * The trade loses 21.4 -> 20 but lives through 21.4 -> 14
+        let expected = (dec!(14) / dec!(21.4) - Decimal::ONE).round_dp(10);
+        assert!(summary.maximum_drawdown.round_dp(10) == expected);
+        assert!(result.equity_curve[16].drawdown == summary.maximum_drawdown);
+        let analysis = result.get_drawdown_analysis(1).unwrap();
+        assert!(analysis.episodes[0].peak_index == 10);
+        assert!(analysis.episodes[0].trough_index == 17);
+        assert!(analysis.episodes[0].recovery_index.is_none());
+        // * Long from bar 9 until the close at bar 21
+        assert!(summary.exposure == dec!(12) / dec!(22));
+        assert!(summary.sharpe_ratio < Decimal::ZERO);
+        // * 22 daily bars are too short for a CAGR
+        assert!(summary.cagr == Decimal::ZERO);
+        assert!(summary.calmar_ratio == Decimal::ZERO);
+    }
+}
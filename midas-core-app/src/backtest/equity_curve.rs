@@ -0,0 +1,77 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::error::MidasError;
+
+/// Portfolio marked to the close of one bar
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct IEquityPoint<T> {
+    pub timestamp: i64,
+    pub index: usize,
+    pub cash: T,
+    /// Quantity held, negative for a short
+    pub quantity: T,
+    /// Position valued at the close, negative for a short
+    pub position_value: T,
+    /// Cash plus position value
+    pub equity: T,
+    /// Fall of equity from its running peak, `-0.1` is 10% below the peak
+    pub drawdown: T,
+}
+
+impl IEquityPoint<Decimal> {
+    /// Point at `close`, `peak` is the highest equity before this bar including the initial capital
+    pub fn new(
+        timestamp: i64,
+        index: usize,
+        cash: Decimal,
+        quantity: Decimal,
+        close: Decimal,
+        peak: Decimal,
+    ) -> Result<IEquityPoint<Decimal>, MidasError> {
+        let position_value = quantity * close;
+        let equity = cash + position_value;
+        let peak = peak.max(equity);
+        let drawdown = (equity - peak)
+            .checked_div(peak)
+            .ok_or_else(|| MidasError::ArithmeticOverflow(format!("drawdown at bar {index}")))?;
+        Ok(IEquityPoint {
+            timestamp,
+            index,
+            cash,
+            quantity,
+            position_value,
+            equity,
+            drawdown,
+        })
+    }
+}
+
+/// Deepest drawdown of the curve, zero for an empty curve
+pub fn get_curve_maximum_drawdown(curve: &[IEquityPoint<Decimal>]) -> Decimal {
+    curve
+        .iter()
+        .map(|point| point.drawdown)
+        .min()
+        .unwrap_or(Decimal::ZERO)
+        .min(Decimal::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_equity_point() {
+        let point = IEquityPoint::new(0, 3, dec!(100), dec!(-10), dec!(4), dec!(80)).unwrap();
+        assert!(point.position_value == dec!(-40));
+        assert!(point.equity == dec!(60));
+        assert!(point.drawdown == dec!(-0.25));
+
+        let high = IEquityPoint::new(0, 4, dec!(100), dec!(0), dec!(4), dec!(80)).unwrap();
+        assert!(high.drawdown == Decimal::ZERO);
+        assert!(get_curve_maximum_drawdown(&[point, high]) == dec!(-0.25));
+        assert!(get_curve_maximum_drawdown(&[]) == Decimal::ZERO);
+    }
+}
@@ -0,0 +1,71 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::get_price::candle::Candle;
+
+/// When and at which price the signal of a bar is filled
+/// Same-bar fills are optimistic, the close is only known once the bar producing the signal is over
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FillTiming {
+    #[default]
+    SameBarClose,
+    NextBarOpen,
+    NextBarClose,
+    /// Typical price `(high + low + close) / 3` of the next bar, the closest to its VWAP a single
+    /// OHLCV bar allows
+    NextBarVwap,
+}
+
+impl FillTiming {
+    /// Bars between the signal and its fill
+    pub fn delay(&self) -> usize {
+        match self {
+            FillTiming::SameBarClose => 0,
+            _ => 1,
+        }
+    }
+    /// Price before costs on `candle`, the bar the order is filled on
+    pub fn price(&self, candle: &Candle) -> Decimal {
+        match self {
+            FillTiming::SameBarClose | FillTiming::NextBarClose => candle.close,
+            FillTiming::NextBarOpen => candle.open,
+            FillTiming::NextBarVwap => (candle.high + candle.low + candle.close) / Decimal::from(3),
+        }
+    }
+}
+
+/// What happens to a position still open on the last bar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EndOfData {
+    /// Close it at the last close, like any other trade
+    ForceClose,
+    /// Leave it open, marked to the last close in the equity curve and `IBackTestingResult::open_position`
+    #[default]
+    ReportOpen,
+    /// Drop it as if it had never been opened, its capital stays in cash
+    Exclude,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_fill_timing_price() {
+        let candle = Candle {
+            timestamp: 0,
+            open: dec!(10),
+            high: dec!(13),
+            low: dec!(9),
+            close: dec!(11),
+            volume: dec!(1),
+        };
+
+        assert!(FillTiming::SameBarClose.price(&candle) == dec!(11));
+        assert!(FillTiming::NextBarOpen.price(&candle) == dec!(10));
+        assert!(FillTiming::NextBarVwap.price(&candle) == dec!(11));
+        assert!(FillTiming::SameBarClose.delay() == 0);
+        assert!(FillTiming::NextBarClose.delay() == 1);
+    }
+}
@@ -0,0 +1,201 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::{IBackTestingResult, POSITION};
+use crate::error::MidasError;
+use crate::get_price::candle::PriceSeries;
+use crate::risk_management::exit_levels::ExitReason;
+
+/// One closed position of the backtest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ITradeRecord {
+    /// `Long` or `Short`
+    pub position: POSITION,
+    pub entry_timestamp: i64,
+    pub exit_timestamp: i64,
+    pub entry_index: usize,
+    pub exit_index: usize,
+    /// Bars between the entry and the exit fill
+    pub holding_bars: usize,
+    /// Time between the entry and the exit fill, in milliseconds
+    pub holding_period: i64,
+    pub quantity: Decimal,
+    /// Prices before costs, see `IBackTestingSingleResult::price`
+    pub entry_price: Decimal,
+    pub exit_price: Decimal,
+    pub entry_fill_price: Decimal,
+    pub exit_fill_price: Decimal,
+    pub fees: Decimal,
+    /// Net profit in quote currency
+    pub pnl: Decimal,
+    /// Net return on the entry notional
+    pub returns: Decimal,
+    pub gross_returns: Decimal,
+    pub exit_reason: Option<ExitReason>,
+    /// Maximum adverse excursion, the worst unrealized move against the trade as a fraction of the
+    /// entry price, `-0.1` is 10% against
+    pub mae: Decimal,
+    /// Maximum favorable excursion, the best unrealized move for the trade
+    pub mfe: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ITradeJournal {
+    /// In the order the trades closed
+    pub trades: Vec<ITradeRecord>,
+}
+
+const CSV_HEADER: &str = "position,entry_timestamp,exit_timestamp,entry_index,exit_index,\
+holding_bars,holding_period,quantity,entry_price,exit_price,entry_fill_price,exit_fill_price,\
+fees,pnl,returns,gross_returns,exit_reason,mae,mfe";
+
+impl ITradeJournal {
+    /// Journal of the closed positions of `result`, `series` is the series it was backtested on
+    /// Excursions use the entry and exit prices and the high and low of every bar after the entry
+    /// bar, the exit bar only counts its whole range when the position was closed by a signal
+    pub fn new(
+        result: &IBackTestingResult<Decimal>,
+        series: &PriceSeries,
+    ) -> Result<ITradeJournal, MidasError> {
+        let candles = series.candles();
+        let mut trades: Vec<ITradeRecord> = Vec::new();
+        for ((entry, exit), trade) in result
+            .get_round_trips()
+            .into_iter()
+            .zip(result.get_entry_and_close()?)
+        {
+            if exit.index >= candles.len() {
+                return Err(MidasError::InsufficientData {
+                    required: exit.index + 1,
+                    available: candles.len(),
+                });
+            }
+            let is_long = entry.action == POSITION::Long;
+            let last = match exit.exit_reason {
+                Some(ExitReason::Signal) | Some(ExitReason::EndOfData) | None => exit.index + 1,
+                _ => exit.index,
+            };
+            let start = (entry.index + 1).min(last);
+            let (mut low, mut high) = (entry.price.min(exit.price), entry.price.max(exit.price));
+            for candle in &candles[start..last] {
+                low = low.min(candle.low);
+                high = high.max(candle.high);
+            }
+            let (adverse, favorable) = if is_long {
+                (low - entry.price, high - entry.price)
+            } else {
+                (entry.price - high, entry.price - low)
+            };
+            let excursion = |value: Decimal| {
+                value.checked_div(entry.price).ok_or_else(|| {
+                    MidasError::ArithmeticOverflow(format!(
+                        "excursion of trade at bar {}",
+                        entry.index
+                    ))
+                })
+            };
+
+            trades.push(ITradeRecord {
+                position: entry.action,
+                entry_timestamp: entry.timestamp,
+                exit_timestamp: exit.timestamp,
+                entry_index: entry.index,
+                exit_index: exit.index,
+                holding_bars: exit.index - entry.index,
+                holding_period: exit.timestamp - entry.timestamp,
+                quantity: entry.quantity,
+                entry_price: entry.price,
+                exit_price: exit.price,
+                entry_fill_price: entry.fill_price,
+                exit_fill_price: exit.fill_price,
+                fees: trade.fees,
+                pnl: trade.pnl,
+                returns: trade.returns,
+                gross_returns: trade.gross_returns,
+                exit_reason: exit.exit_reason,
+                mae: excursion(adverse)?,
+                mfe: excursion(favorable)?,
+            });
+        }
+        Ok(ITradeJournal { trades })
+    }
+    /// One line per trade, the exit reason is empty when unknown
+    pub fn to_csv(&self) -> String {
+        let mut lines: Vec<String> = vec![CSV_HEADER.to_string()];
+        for trade in &self.trades {
+            lines.push(
+                [
+                    format!("{:?}", trade.position),
+                    trade.entry_timestamp.to_string(),
+                    trade.exit_timestamp.to_string(),
+                    trade.entry_index.to_string(),
+                    trade.exit_index.to_string(),
+                    trade.holding_bars.to_string(),
+                    trade.holding_period.to_string(),
+                    trade.quantity.to_string(),
+                    trade.entry_price.to_string(),
+                    trade.exit_price.to_string(),
+                    trade.entry_fill_price.to_string(),
+                    trade.exit_fill_price.to_string(),
+                    trade.fees.to_string(),
+                    trade.pnl.to_string(),
+                    trade.returns.to_string(),
+                    trade.gross_returns.to_string(),
+                    trade
+                        .exit_reason
+                        .map(|reason| format!("{reason:?}"))
+                        .unwrap_or_default(),
+                    trade.mae.to_string(),
+                    trade.mfe.to_string(),
+                ]
+                .join(","),
+            );
+        }
+        lines.join("\n")
+    }
+    pub fn to_json(&self) -> Result<String, MidasError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::engine::get_positions_from_signals;
+    use crate::backtest::strategy::Signal;
+    use crate::backtest::IBackTestingParams;
+    use crate::utils::mock::mock_candles;
+    use crate::utils::DAY_IN_MILLISECONDS;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_trade_journal() {
+        let series = PriceSeries::new(mock_candles());
+        let mut signals = vec![None; 22];
+        signals[9] = Some(Signal::Long);
+        signals[21] = Some(Signal::Close);
+        let params = IBackTestingParams::new("mock", 30);
+        let result = get_positions_from_signals(&signals, &series, &params).unwrap();
+
+        let journal = ITradeJournal::new(&result, &series).unwrap();
+        let trade = journal.trades[0];
+
+        assert!(journal.trades.len() == 1);
+        assert!(trade.position == POSITION::Long);
+        assert!(trade.holding_bars == 12);
+        assert!(trade.holding_period == 12 * DAY_IN_MILLISECONDS);
+        assert!(trade.quantity * trade.entry_fill_price == params.initial_capital);
+        assert!(trade.exit_reason == Some(ExitReason::Signal));
+        // * Bought at 21.4, lowest close 14 and never higher than the entry
+        assert!(trade.mae == (dec!(14) - dec!(21.4)) / dec!(21.4));
+        assert!(trade.mfe == Decimal::ZERO);
+        assert!(trade.returns == trade.gross_returns);
+
+        let csv = journal.to_csv();
+        assert!(csv.lines().count() == 2);
+        assert!(csv.lines().nth(1).unwrap().starts_with("Long,"));
+        assert!(csv.lines().nth(1).unwrap().contains(",Signal,"));
+        let json = journal.to_json().unwrap();
+        assert!(serde_json::from_str::<ITradeJournal>(&json).unwrap() == journal);
+    }
+}
@@ -0,0 +1,253 @@
+use num::FromPrimitive;
+use rust_decimal::{Decimal, MathematicalOps};
+
+use super::equity_curve::IEquityPoint;
+use crate::error::MidasError;
+
+/// Conventions used to annualize risk-adjusted metrics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IMetricsParams {
+    /// Annual risk-free rate, e.g. 0.04 for 4%
+    pub risk_free_rate: Decimal,
+    /// Bars per year, 365 for daily crypto bars which trade every day
+    pub periods_per_year: u32,
+}
+
+impl IMetricsParams {
+    pub fn new() -> IMetricsParams {
+        IMetricsParams {
+            risk_free_rate: Decimal::ZERO,
+            periods_per_year: 365,
+        }
+    }
+    /// Risk-free rate of one bar
+    pub fn risk_free_per_period(&self) -> Decimal {
+        self.risk_free_rate / Decimal::from(self.periods_per_year)
+    }
+    fn annualization(&self) -> Decimal {
+        Decimal::from(self.periods_per_year)
+            .sqrt()
+            .unwrap_or(Decimal::ONE)
+    }
+}
+
+impl Default for IMetricsParams {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn length(values: &[Decimal]) -> Result<Decimal, MidasError> {
+    Decimal::from_usize(values.len()).ok_or_else(|| {
+        MidasError::ArithmeticOverflow(format!("{} values as Decimal", values.len()))
+    })
+}
+
+/// Mean and population standard deviation, zero for an empty slice
+pub fn mean_and_deviation(values: &[Decimal]) -> Result<(Decimal, Decimal), MidasError> {
+    if values.is_empty() {
+        return Ok((Decimal::ZERO, Decimal::ZERO));
+    }
+    let length = length(values)?;
+    let mean = values.iter().sum::<Decimal>() / length;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean) * (value - mean))
+        .sum::<Decimal>()
+        / length;
+    Ok((mean, variance.sqrt().unwrap_or(Decimal::ZERO)))
+}
+
+/// Return of every bar, the first one measured from `initial_capital`
+pub fn get_bar_returns(
+    curve: &[IEquityPoint<Decimal>],
+    initial_capital: Decimal,
+) -> Result<Vec<Decimal>, MidasError> {
+    let mut previous = initial_capital;
+    let mut returns: Vec<Decimal> = Vec::with_capacity(curve.len());
+    for point in curve {
+        returns.push(
+            (point.equity - previous)
+                .checked_div(previous)
+                .ok_or_else(|| {
+                    MidasError::ArithmeticOverflow(format!("return at bar {}", point.index))
+                })?,
+        );
+        previous = point.equity;
+    }
+    Ok(returns)
+}
+
+pub fn get_annualized_volatility(
+    returns: &[Decimal],
+    params: &IMetricsParams,
+) -> Result<Decimal, MidasError> {
+    let (_, deviation) = mean_and_deviation(returns)?;
+    Ok(deviation * params.annualization())
+}
+
+/// Annualized excess return over the annualized volatility, zero without volatility
+pub fn get_sharpe_ratio(
+    returns: &[Decimal],
+    params: &IMetricsParams,
+) -> Result<Decimal, MidasError> {
+    let (mean, deviation) = mean_and_deviation(returns)?;
+    Ok((mean - params.risk_free_per_period())
+        .checked_div(deviation)
+        .unwrap_or(Decimal::ZERO)
+        * params.annualization())
+}
+
+/// Like Sharpe, but only returns below the risk-free rate count as risk
+pub fn get_sortino_ratio(
+    returns: &[Decimal],
+    params: &IMetricsParams,
+) -> Result<Decimal, MidasError> {
+    if returns.is_empty() {
+        return Ok(Decimal::ZERO);
+    }
+    let risk_free = params.risk_free_per_period();
+    let (mean, _) = mean_and_deviation(returns)?;
+    let downside = returns
+        .iter()
+        .map(|value| (value - risk_free).min(Decimal::ZERO))
+        .map(|value| value * value)
+        .sum::<Decimal>()
+        / length(returns)?;
+    Ok((mean - risk_free)
+        .checked_div(downside.sqrt().unwrap_or(Decimal::ZERO))
+        .unwrap_or(Decimal::ZERO)
+        * params.annualization())
+}
+
+/// Compound annual growth rate of `total_return` over `periods` bars
+/// Zero below one year of bars, extrapolating a short backtest to a year is meaningless and can
+/// overflow a `Decimal`
+pub fn get_cagr(
+    total_return: Decimal,
+    periods: usize,
+    params: &IMetricsParams,
+) -> Result<Decimal, MidasError> {
+    let years = Decimal::from_usize(periods)
+        .ok_or_else(|| MidasError::ArithmeticOverflow(format!("{periods} bars as Decimal")))?
+        / Decimal::from(params.periods_per_year);
+    if years < Decimal::ONE {
+        return Ok(Decimal::ZERO);
+    }
+    let growth = Decimal::ONE + total_return;
+    if growth <= Decimal::ZERO {
+        return Ok(Decimal::NEGATIVE_ONE);
+    }
+    growth
+        .checked_powd(Decimal::ONE / years)
+        .map(|value| value - Decimal::ONE)
+        .ok_or_else(|| MidasError::ArithmeticOverflow(format!("{growth} to the power 1 / {years}")))
+}
+
+/// SQN, the square root of the trade count times mean over deviation of trade returns
+pub fn get_sqn(returns: &[Decimal]) -> Result<Decimal, MidasError> {
+    let (mean, deviation) = mean_and_deviation(returns)?;
+    let root = length(returns)?.sqrt().unwrap_or(Decimal::ZERO);
+    Ok(root * mean.checked_div(deviation).unwrap_or(Decimal::ZERO))
+}
+
+/// Root mean square of the drawdowns of the curve
+pub fn get_ulcer_index(curve: &[IEquityPoint<Decimal>]) -> Result<Decimal, MidasError> {
+    let drawdowns: Vec<Decimal> = curve.iter().map(|point| point.drawdown).collect();
+    if drawdowns.is_empty() {
+        return Ok(Decimal::ZERO);
+    }
+    let squared = drawdowns.iter().map(|value| value * value).sum::<Decimal>();
+    Ok((squared / length(&drawdowns)?)
+        .sqrt()
+        .unwrap_or(Decimal::ZERO))
+}
+
+/// Share of bars with an open position
+pub fn get_exposure(curve: &[IEquityPoint<Decimal>]) -> Decimal {
+    let exposed = curve
+        .iter()
+        .filter(|point| !point.quantity.is_zero())
+        .count();
+    Decimal::from(exposed)
+        .checked_div(Decimal::from(curve.len()))
+        .unwrap_or(Decimal::ZERO)
+}
+
+/// Net profit over the largest fall of equity from a peak, both in quote currency
+pub fn get_recovery_factor(curve: &[IEquityPoint<Decimal>], initial_capital: Decimal) -> Decimal {
+    let mut peak = initial_capital;
+    let mut largest_fall = Decimal::ZERO;
+    for point in curve {
+        peak = peak.max(point.equity);
+        largest_fall = largest_fall.max(peak - point.equity);
+    }
+    let net_profit = curve
+        .last()
+        .map(|point| point.equity - initial_capital)
+        .unwrap_or(Decimal::ZERO);
+    net_profit
+        .checked_div(largest_fall)
+        .unwrap_or(Decimal::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn curve(equities: &[Decimal]) -> Vec<IEquityPoint<Decimal>> {
+        let mut peak = dec!(100);
+        equities
+            .iter()
+            .enumerate()
+            .map(|(index, equity)| {
+                let point = IEquityPoint::new(0, index, *equity, dec!(0), dec!(0), peak).unwrap();
+                peak = peak.max(*equity);
+                point
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ratios() {
+        let params = IMetricsParams {
+            risk_free_rate: dec!(0),
+            periods_per_year: 4,
+        };
+        let returns = [dec!(0.1), dec!(-0.1), dec!(0.1), dec!(-0.1)];
+
+        assert!(get_annualized_volatility(&returns, &params).unwrap() == dec!(0.2));
+        assert!(get_sharpe_ratio(&returns, &params).unwrap() == Decimal::ZERO);
+        // * Mean 0.05, downside deviation sqrt(0.01 / 2)
+        let returns = [dec!(0.2), dec!(-0.1)];
+        let sortino = get_sortino_ratio(&returns, &params).unwrap();
+        assert!(sortino.round_dp(6) == (dec!(0.1) / dec!(0.005).sqrt().unwrap()).round_dp(6));
+        assert!(get_sqn(&[dec!(0.1), dec!(0.3)]).unwrap().round_dp(6) == dec!(2.828427));
+        // * 21% over two years of four bars
+        let cagr = get_cagr(dec!(0.21), 8, &params).unwrap();
+        assert!(cagr.round_dp(6) == dec!(0.1));
+        assert!(get_cagr(dec!(1.14), 3, &IMetricsParams::new()).unwrap() == Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_curve_metrics() {
+        let curve = curve(&[dec!(110), dec!(88), dec!(99), dec!(121)]);
+
+        let returns = get_bar_returns(&curve, dec!(100)).unwrap();
+        assert!(
+            returns
+                == vec![
+                    dec!(0.1),
+                    dec!(-0.2),
+                    dec!(0.125),
+                    dec!(0.2222222222222222222222222222)
+                ]
+        );
+        // * Drawdowns are 0, -0.2, -0.1, 0
+        assert!(get_ulcer_index(&curve).unwrap() == dec!(0.0125).sqrt().unwrap());
+        assert!(get_exposure(&curve) == Decimal::ZERO);
+        // * Profit 21 over a fall from 110 to 88
+        assert!(get_recovery_factor(&curve, dec!(100)) == dec!(21) / dec!(22));
+    }
+}
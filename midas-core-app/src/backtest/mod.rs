@@ -1,19 +1,86 @@
-use std::ops::{Div, Sub};
+use std::ops::{Add, Div, Mul, Sub};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::get_price::historical_price::get_historical_chart_data;
-use crate::price_manipulation::returns::{get_average_return, get_return};
+use crate::error::MidasError;
+use crate::get_price::candle::PriceSeries;
+use crate::get_price::source::PriceSource;
+use crate::price_manipulation::drawdown::{get_drawdown_analysis, IDrawdownAnalysis};
+use crate::price_manipulation::returns::get_average_return;
+use crate::risk_management::exit_levels::{ExitReason, IExitRules};
 
-use crate::ta_rs::cdc_action_zone::get_cdc_action_zone;
-use crate::ta_rs::ma::get_ma_cross;
+use crate::utils::DAY_IN_MILLISECONDS;
+use num::CheckedDiv;
 
 use rust_decimal::prelude::Decimal;
+use rust_decimal_macros::dec;
+use std::collections::HashMap;
 
+pub mod benchmark;
+pub mod costs;
+pub mod engine;
+pub mod equity_curve;
+pub mod execution;
+pub mod journal;
+pub mod metrics;
+pub mod monte_carlo;
+pub mod optimizer;
+pub mod portfolio;
+pub mod sizing;
+pub mod strategy;
 pub mod summary;
+pub mod walk_forward;
+use benchmark::get_aligned_benchmark_returns;
+pub use costs::ICostModel;
+use engine::{get_positions_from_signals, run_strategy};
+pub use equity_curve::IEquityPoint;
+pub use execution::{EndOfData, FillTiming};
+use journal::ITradeJournal;
+pub use metrics::IMetricsParams;
+use monte_carlo::{monte_carlo, IMonteCarloParams, IMonteCarloResult};
 use serde::{Deserialize, Serialize};
+pub use sizing::PositionSizing;
+use strategy::cross_signals;
+pub use strategy::{Signal, Strategy};
 pub use summary::IBackTestingSummary;
+#[derive(Debug, Clone)]
 pub struct IBackTestingParams<'a> {
     pub coin_id: &'a str,
+    /// Number of days of history, counted back from now
     pub period: u32,
+    /// Open short positions on `Signal::Short`, otherwise short signals only close longs
+    pub allow_short: bool,
+    /// Starting equity in quote currency
+    pub initial_capital: Decimal,
+    pub costs: ICostModel,
+    /// Stop-loss, take-profit and trailing stop attached to every opened position
+    pub exits: IExitRules,
+    pub sizing: PositionSizing,
+    /// Bar and price signals are filled at, the signal bar close by default
+    pub fill: FillTiming,
+    /// Fail `run_strategy` when a strategy's signals change once later bars are hidden,
+    /// replays the strategy once per bar
+    pub check_look_ahead: bool,
+    pub end_of_data: EndOfData,
+    /// Symbol compared against in `start_backtesting`, e.g. "bitcoin", `None` compares with holding `coin_id`
+    pub benchmark: Option<&'a str>,
+}
+
+impl<'a> IBackTestingParams<'a> {
+    pub fn new(coin_id: &'a str, period: u32) -> IBackTestingParams<'a> {
+        IBackTestingParams {
+            coin_id,
+            period,
+            allow_short: false,
+            initial_capital: dec!(10000),
+            costs: ICostModel::new(),
+            exits: IExitRules::default(),
+            sizing: PositionSizing::AllIn,
+            fill: FillTiming::SameBarClose,
+            check_look_ahead: false,
+            end_of_data: EndOfData::ReportOpen,
+            benchmark: None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -22,169 +89,566 @@ pub struct IBackTestingIndicator<T> {
     pub ma_cross: T,
 }
 impl IBackTestingIndicator<Decimal> {
+    /// Long when `indicator` crosses above zero, close the long when it crosses below
+    /// With `params.allow_short`, crossing below also opens a short which is covered on the next cross above
+    /// `gap` is the indicator warm up, i.e. `indicator[0]` belongs to bar `gap` of `series`
     pub fn get_positions(
         indicator: Vec<Decimal>,
-        prices: &[Decimal],
+        series: &PriceSeries,
         gap: usize,
-    ) -> IBackTestingResult<Decimal> {
-        let mut positions: IBackTestingResult<Decimal> = IBackTestingResult { result: Vec::new() };
-        for index in 1..indicator.len() {
-            if indicator[index] > Decimal::ZERO && indicator[index - 1] < Decimal::ZERO {
-                positions.result.push(IBackTestingSingleResult {
-                    action: POSITION::Long,
-                    price: prices[index + gap],
-                })
-            } else if indicator[index] < Decimal::ZERO
-                && indicator[index - 1] > Decimal::ZERO
-                && !positions.result.is_empty()
-            {
-                if let POSITION::Long = positions.result[positions.result.len() - 1].action {
-                    positions.result.push(IBackTestingSingleResult {
-                        action: POSITION::Close,
-                        price: prices[index + gap],
-                    })
-                }
-            }
+        params: &IBackTestingParams,
+    ) -> Result<IBackTestingResult<Decimal>, MidasError> {
+        if indicator.len() + gap > series.len() {
+            return Err(MidasError::InsufficientData {
+                required: indicator.len() + gap,
+                available: series.len(),
+            });
         }
-        positions
+        let signals = cross_signals(&indicator, gap, series.len());
+        get_positions_from_signals(&signals, series, params)
     }
 }
 
-pub struct IPreBacktesting<T, K> {
-    pub prices: Box<[T]>,
-    pub returns: Vec<T>,
-    pub indicators: IBackTestingIndicator<K>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct IBackTestingSingleResult<T> {
     pub action: POSITION,
+    /// Price given by `IBackTestingParams::fill`, or the crossed level for a stop or target exit,
+    /// before any cost
     pub price: T,
+    /// Timestamp of the bar the position was taken on, in unix milliseconds
+    pub timestamp: i64,
+    /// Index of that bar in the backtested `PriceSeries`
+    pub index: usize,
+    /// Price after spread and slippage
+    pub fill_price: T,
+    pub quantity: T,
+    /// Fee paid on this fill, in quote currency
+    pub fee: T,
+    /// Why the position was closed, `None` on entries
+    pub exit_reason: Option<ExitReason>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IBackTestingResult<T> {
     pub result: Vec<IBackTestingSingleResult<T>>,
+    /// One point per bar, empty when the result was not replayed by the engine
+    pub equity_curve: Vec<IEquityPoint<T>>,
+    pub initial_capital: T,
+    /// Benchmark return of every bar of `equity_curve`, buy and hold of the backtested coin by default
+    pub benchmark: Vec<T>,
+    /// Position left open on the last bar with `EndOfData::ReportOpen`, it is not part of the trades
+    pub open_position: Option<IOpenPosition<T>>,
+}
+
+/// Position still open at the end of the data, marked to the last close
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct IOpenPosition<T> {
+    /// Fill that opened the position
+    pub entry: IBackTestingSingleResult<T>,
+    pub mark_price: T,
+    /// Negative for a short
+    pub market_value: T,
+    /// Profit if closed at `mark_price` before exit costs, the entry fee is included
+    pub unrealized_pnl: T,
+    /// `unrealized_pnl` over the entry notional
+    pub unrealized_return: T,
 }
 
-impl<T: Sized + core::fmt::Debug + Copy + Sub<Output = T> + Div<Output = T>> IBackTestingResult<T> {
+impl IOpenPosition<Decimal> {
+    pub fn new(
+        entry: IBackTestingSingleResult<Decimal>,
+        mark_price: Decimal,
+    ) -> IOpenPosition<Decimal> {
+        let (market_value, gain) = if entry.action == POSITION::Short {
+            (-entry.quantity * mark_price, entry.fill_price - mark_price)
+        } else {
+            (entry.quantity * mark_price, mark_price - entry.fill_price)
+        };
+        let unrealized_pnl = gain * entry.quantity - entry.fee;
+        IOpenPosition {
+            entry,
+            mark_price,
+            market_value,
+            unrealized_pnl,
+            unrealized_return: unrealized_pnl
+                .checked_div(entry.fill_price * entry.quantity)
+                .unwrap_or(Decimal::ZERO),
+        }
+    }
+}
+
+impl<
+        T: Sized
+            + core::fmt::Debug
+            + Copy
+            + Add<Output = T>
+            + Sub<Output = T>
+            + Mul<Output = T>
+            + Div<Output = T>
+            + CheckedDiv,
+    > IBackTestingResult<T>
+{
     pub fn get_price(&self) -> Box<[T]> {
         self.result
             .iter()
-            .map(|IBackTestingSingleResult { action: _, price }| *price)
+            .map(|IBackTestingSingleResult { price, .. }| *price)
             .collect::<Box<[T]>>()
     }
-    pub fn get_entry_and_close(&self) -> Vec<IBacktestingReturn<T>> {
+    /// Pair every `Long` with the following `Close` and every `Short` with the following `Cover`
+    /// Returns are signed, a short gains when the price falls
+    /// Gross returns use bar prices, net returns use fill prices and fees relative to the entry notional
+    /// A round trip entered at a zero price or with a zero notional is an `ArithmeticOverflow`
+    pub fn get_entry_and_close(&self) -> Result<Vec<IBacktestingReturn<T>>, MidasError> {
         let mut store: Vec<IBacktestingReturn<T>> = Vec::new();
-        let mut long_position: T = self.result[0].price;
-        for result in &self.result {
-            if result.action == POSITION::Long {
-                long_position = result.price
+        for (entry, result) in self.get_round_trips() {
+            let notional = entry.fill_price * entry.quantity;
+            let fees = entry.fee + result.fee;
+            let (gain, pnl) = if entry.action == POSITION::Long {
+                (
+                    result.price - entry.price,
+                    (result.fill_price - entry.fill_price) * entry.quantity - fees,
+                )
             } else {
-                store.push(IBacktestingReturn {
-                    entry: long_position,
-                    closing: result.price,
-                    returns: (result.price - long_position) / long_position,
-                })
-            }
+                (
+                    entry.price - result.price,
+                    (entry.fill_price - result.fill_price) * entry.quantity - fees,
+                )
+            };
+            let overflow = || {
+                MidasError::ArithmeticOverflow(format!(
+                    "return of the trade entered at bar {}",
+                    entry.index
+                ))
+            };
+            store.push(IBacktestingReturn {
+                position: entry.action,
+                entry: entry.price,
+                closing: result.price,
+                returns: pnl.checked_div(&notional).ok_or_else(overflow)?,
+                gross_returns: gain.checked_div(&entry.price).ok_or_else(overflow)?,
+                pnl,
+                fees,
+                exit_reason: result.exit_reason,
+            });
         }
 
-        store
+        Ok(store)
+    }
+    /// Entry and exit fill of every closed position, in the order they closed
+    fn get_round_trips(&self) -> Vec<(&IBackTestingSingleResult<T>, &IBackTestingSingleResult<T>)> {
+        let mut round_trips = Vec::new();
+        let mut open_position: Option<&IBackTestingSingleResult<T>> = None;
+        for result in &self.result {
+            let entry = match (result.action, open_position) {
+                (POSITION::Long, _) | (POSITION::Short, _) => {
+                    open_position = Some(result);
+                    continue;
+                }
+                (POSITION::Close, Some(entry)) if entry.action == POSITION::Long => entry,
+                (POSITION::Cover, Some(entry)) if entry.action == POSITION::Short => entry,
+                _ => continue,
+            };
+            round_trips.push((entry, result));
+            open_position = None;
+        }
+        round_trips
+    }
+    pub fn get_return(&self) -> Result<Vec<T>, MidasError> {
+        let entry_and_close = self.get_entry_and_close()?;
+        Ok(entry_and_close
+            .iter()
+            .map(|IBacktestingReturn { returns, .. }| *returns)
+            .collect::<Vec<T>>())
     }
-    pub fn get_return(&self) -> Vec<T> {
-        let entry_and_close = self.get_entry_and_close();
-        entry_and_close
+    /// Returns before spread, slippage and fees
+    pub fn get_gross_return(&self) -> Result<Vec<T>, MidasError> {
+        Ok(self
+            .get_entry_and_close()?
             .iter()
-            .map(
-                |IBacktestingReturn {
-                     entry: _,
-                     closing: _,
-                     returns,
-                 }| *returns,
-            )
-            .collect::<Vec<T>>()
+            .map(|IBacktestingReturn { gross_returns, .. }| *gross_returns)
+            .collect::<Vec<T>>())
     }
 }
 
 impl IBackTestingResult<Decimal> {
-    async fn get_average_return(&self) -> Decimal {
-        get_average_return(self.get_return()).await
+    async fn get_average_return(&self) -> Result<Decimal, MidasError> {
+        get_average_return(self.get_return()?).await
     }
-    pub fn filter_positive_return(&self) -> Vec<Decimal> {
-        self.get_return()
-            .iter()
-            .filter(|value| **value > Decimal::ZERO)
-            .copied()
-            .collect::<Vec<Decimal>>()
+    pub fn filter_positive_return(&self) -> Result<Vec<Decimal>, MidasError> {
+        Ok(self
+            .get_return()?
+            .into_iter()
+            .filter(|value| *value > Decimal::ZERO)
+            .collect::<Vec<Decimal>>())
     }
-    pub fn filter_negative_return(&self) -> Vec<Decimal> {
-        self.get_return()
-            .iter()
-            .filter(|value| **value < Decimal::ZERO)
-            .copied()
-            .collect::<Vec<Decimal>>()
+    pub fn filter_negative_return(&self) -> Result<Vec<Decimal>, MidasError> {
+        Ok(self
+            .get_return()?
+            .into_iter()
+            .filter(|value| *value < Decimal::ZERO)
+            .collect::<Vec<Decimal>>())
     }
-    pub async fn get_summary(&self) -> IBackTestingSummary {
+    pub async fn get_summary(&self) -> Result<IBackTestingSummary, MidasError> {
         IBackTestingSummary::calculate(self)
     }
+    /// Compare with holding `benchmark` instead of the backtested coin
+    pub fn set_benchmark(&mut self, benchmark: &PriceSeries) -> Result<(), MidasError> {
+        self.benchmark = get_aligned_benchmark_returns(benchmark, &self.equity_curve)?;
+        Ok(())
+    }
+    /// Every closed trade with its excursions, `series` is the series this result was backtested on
+    pub fn get_trade_journal(&self, series: &PriceSeries) -> Result<ITradeJournal, MidasError> {
+        ITradeJournal::new(self, series)
+    }
+    /// Monte Carlo distributions of the net trade returns
+    pub fn get_monte_carlo(
+        &self,
+        params: &IMonteCarloParams,
+    ) -> Result<IMonteCarloResult, MidasError> {
+        monte_carlo(&self.get_return()?, params)
+    }
+    /// Drawdowns of the equity curve, index 0 is the initial capital and index `i + 1` is bar `i`
+    pub fn get_drawdown_analysis(&self, top: usize) -> Result<IDrawdownAnalysis, MidasError> {
+        let equity: Vec<Decimal> = std::iter::once(self.initial_capital)
+            .chain(self.equity_curve.iter().map(|point| point.equity))
+            .collect();
+        get_drawdown_analysis(&equity, top)
+    }
+    pub async fn get_summary_with_metrics(
+        &self,
+        metrics: &IMetricsParams,
+    ) -> Result<IBackTestingSummary, MidasError> {
+        IBackTestingSummary::calculate_with_metrics(self, metrics)
+    }
 }
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IBacktestingReturn<T> {
+    /// Direction of the trade, `Long` or `Short`
+    pub position: POSITION,
     pub entry: T,
     pub closing: T,
+    /// Net of spread, slippage and fees
     pub returns: T,
+    pub gross_returns: T,
+    /// Net profit in quote currency
+    pub pnl: T,
+    /// Entry and closing fees, in quote currency
+    pub fees: T,
+    pub exit_reason: Option<ExitReason>,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum POSITION {
     Long,
+    /// Close a long position
     Close,
+    Short,
+    /// Close a short position
+    Cover,
 }
 
-// TODO: Dynamic trading strategies testing
-
 // TODO: Average return of backtest should be limited risk
 
-async fn pre_backtest(
-    IBackTestingParams { coin_id, .. }: IBackTestingParams<'_>,
-) -> Result<IPreBacktesting<Decimal, Vec<Decimal>>, String> {
-    let prices: Box<[Decimal]> = get_historical_chart_data(coin_id).await.extract_prices();
-    let returns: Vec<Decimal> = get_return(&prices).await;
-
-    let indicators: IBackTestingIndicator<Vec<Decimal>> = IBackTestingIndicator {
-        // sma: simple_moving_average(IMAParams { prices: &prices, period: 12 }).unwrap(),
-        // ema: exponential_moving_average(IMAParams { prices: &prices, period: 12 }).unwrap(),
-        cdc: get_cdc_action_zone(&prices).await.unwrap(),
-        ma_cross: get_ma_cross(&prices, 14, 26).unwrap(),
-    };
+/// `period` days back from now, in unix milliseconds
+fn get_window(period: u32) -> Result<(i64, i64), MidasError> {
+    let to: i64 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| MidasError::InvalidInput(e.to_string()))?
+        .as_millis() as i64;
+    Ok((to - i64::from(period) * DAY_IN_MILLISECONDS, to))
+}
 
-    Ok(IPreBacktesting {
-        prices,
-        returns,
-        indicators,
-    })
+/// Candles of `params.period` days, the strategies compute whatever they need from them
+async fn pre_backtest<S: PriceSource>(
+    source: &S,
+    IBackTestingParams {
+        coin_id, period, ..
+    }: &IBackTestingParams<'_>,
+) -> Result<PriceSeries, MidasError> {
+    let (from, to) = get_window(*period)?;
+    source.get_candles(coin_id, from, to).await
 }
 
-pub async fn start_backtesting(
+/// Run every strategy on the same price series, results are keyed by `Strategy::name`
+/// `strategy::default_strategies()` gives the CDC Action Zone and MA cross strategies
+/// Results are benchmarked against `params.benchmark` when set, otherwise against holding the coin
+pub async fn start_backtesting<S: PriceSource>(
+    source: &S,
     params: IBackTestingParams<'_>,
-) -> IBackTestingIndicator<IBackTestingResult<Decimal>> {
-    let IBackTestingParams { coin_id, period } = params;
-    let IPreBacktesting {
-        prices,
-        returns,
-        indicators,
-    } = if let Ok(value) = pre_backtest(IBackTestingParams { coin_id, period }).await {
-        value
-    } else {
-        panic!("Fail to run pre backtest")
+    strategies: Vec<Box<dyn Strategy>>,
+) -> Result<HashMap<String, IBackTestingResult<Decimal>>, MidasError> {
+    let series: PriceSeries = pre_backtest(source, &params).await?;
+    let benchmark: Option<PriceSeries> = match params.benchmark {
+        Some(symbol) => {
+            let (from, to) = get_window(params.period)?;
+            Some(source.get_candles(symbol, from, to).await?)
+        }
+        None => None,
     };
 
-    let IBackTestingIndicator { cdc, ma_cross } = indicators;
+    let mut results: HashMap<String, IBackTestingResult<Decimal>> = HashMap::new();
+    for mut strategy in strategies {
+        let name = strategy.name();
+        if results.contains_key(&name) {
+            return Err(MidasError::InvalidInput(format!(
+                "Duplicate strategy name {name}"
+            )));
+        }
+        let mut result = run_strategy(strategy.as_mut(), &series, &params)?;
+        if let Some(benchmark) = &benchmark {
+            result.set_benchmark(benchmark)?;
+        }
+        results.insert(name, result);
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_price::candle::Candle;
+    use crate::get_price::source::InMemorySource;
+    use crate::utils::mock::{mock_candles, mock_fill};
+
+    #[test]
+    fn test_get_positions_records_bar() {
+        let series = PriceSeries::new(mock_candles());
+        let indicator = vec![dec!(-1), dec!(1), dec!(1), dec!(-1)];
+        let params = IBackTestingParams::new("mock", 30);
+
+        let positions =
+            IBackTestingIndicator::get_positions(indicator.clone(), &series, 2, &params).unwrap();
 
-    let gap = prices.len() - cdc.len();
+        assert!(positions.result.len() == 2);
+        assert!(positions.result[0].action == POSITION::Long);
+        assert!(positions.result[0].index == 3);
+        assert!(positions.result[0].price == series.candles()[3].close);
+        assert!(positions.result[0].timestamp == series.candles()[3].timestamp);
+        assert!(positions.result[1].action == POSITION::Close);
+        assert!(positions.result[1].index == 5);
 
-    let cdc = IBackTestingIndicator::get_positions(cdc, &prices, gap);
-    let ma_cross = IBackTestingIndicator::get_positions(ma_cross, &prices, gap);
+        let params = IBackTestingParams {
+            allow_short: true,
+            ..params
+        };
+        let positions =
+            IBackTestingIndicator::get_positions(indicator, &series, 2, &params).unwrap();
+        let actions: Vec<POSITION> = positions
+            .result
+            .iter()
+            .map(|position| position.action)
+            .collect();
+        assert!(actions == vec![POSITION::Long, POSITION::Close, POSITION::Short]);
+    }
+
+    #[test]
+    fn test_get_entry_and_close_short() {
+        let result = IBackTestingResult {
+            result: vec![
+                mock_fill(POSITION::Short, dec!(20), 0),
+                mock_fill(POSITION::Cover, dec!(15), 1),
+                mock_fill(POSITION::Long, dec!(15), 1),
+                mock_fill(POSITION::Close, dec!(18), 2),
+                mock_fill(POSITION::Short, dec!(18), 2),
+                mock_fill(POSITION::Cover, dec!(27), 3),
+            ],
+            equity_curve: Vec::new(),
+            initial_capital: dec!(1),
+            benchmark: Vec::new(),
+            open_position: None,
+        };
+
+        let trades = result.get_entry_and_close().unwrap();
+
+        assert!(trades.len() == 3);
+        assert!(trades[0].position == POSITION::Short);
+        assert!(trades[0].returns == dec!(0.25));
+        assert!(trades[1].position == POSITION::Long);
+        assert!(trades[1].returns == dec!(0.2));
+        assert!(trades[2].returns == dec!(-0.5));
+    }
+
+    #[test]
+    fn test_get_entry_and_close_zero_price() {
+        let result = IBackTestingResult {
+            result: vec![
+                mock_fill(POSITION::Long, dec!(0), 0),
+                mock_fill(POSITION::Close, dec!(5), 1),
+            ],
+            equity_curve: Vec::new(),
+            initial_capital: dec!(1),
+            benchmark: Vec::new(),
+            open_position: None,
+        };
+
+        assert!(matches!(
+            result.get_entry_and_close(),
+            Err(MidasError::ArithmeticOverflow(_))
+        ));
+        assert!(IBackTestingSummary::calculate(&result).is_err());
+    }
+
+    /// Mock candles shifted so the last one is yesterday
+    fn recent_candles() -> Vec<Candle> {
+        let mut candles = mock_candles();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+        let length = candles.len() as i64;
+        for (index, candle) in candles.iter_mut().enumerate() {
+            candle.timestamp = now - (length - index as i64) * DAY_IN_MILLISECONDS;
+        }
+        candles
+    }
+
+    fn recent_source() -> InMemorySource {
+        let mut source = InMemorySource::new();
+        source.insert("mock", recent_candles());
+        source
+    }
+
+    /// Long below 15, close above 20
+    struct Threshold;
+
+    impl Strategy for Threshold {
+        fn name(&self) -> String {
+            "threshold".to_string()
+        }
+        fn next(
+            &mut self,
+            bars: &[crate::get_price::candle::Candle],
+        ) -> Result<Option<Signal>, MidasError> {
+            let close = bars[bars.len() - 1].close;
+            Ok(if close < dec!(15) {
+                Some(Signal::Long)
+            } else if close > dec!(20) {
+                Some(Signal::Close)
+            } else {
+                None
+            })
+        }
+    }
 
-    IBackTestingIndicator { cdc, ma_cross }
+    #[tokio::test]
+    async fn test_start_backtesting_in_memory() {
+        let mut strategies = strategy::default_strategies();
+        strategies.push(Box::new(Threshold));
+
+        let results = start_backtesting(
+            &recent_source(),
+            IBackTestingParams::new("mock", 60),
+            strategies,
+        )
+        .await
+        .unwrap();
+
+        assert!(results.len() == 3);
+        assert!(results["cdc"].result.is_empty());
+        assert!(results["ma_cross"].result.is_empty());
+
+        let threshold = results["threshold"].get_entry_and_close().unwrap();
+        // * 2 -> 20.5 and 14 -> 20.33
+        assert!(threshold.len() == 2);
+        assert!(threshold[0].entry == dec!(2) && threshold[0].closing == dec!(20.5));
+        assert!(threshold[1].entry == dec!(14) && threshold[1].closing == dec!(20.33));
+
+        // * Holding the coin from 2 to 24.12 while the CDC strategy never trades
+        let summary = results["cdc"].get_summary().await.unwrap();
+        assert!(summary.benchmark_return == dec!(11.06));
+        assert!(summary.excess_return == dec!(-11.06));
+        assert!(summary.beta == Decimal::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_start_backtesting_zero_close() {
+        let mut candles = recent_candles();
+        candles[10].close = Decimal::ZERO;
+        let mut source = InMemorySource::new();
+        source.insert("mock", candles);
+
+        let results = start_backtesting(
+            &source,
+            IBackTestingParams::new("mock", 60),
+            strategy::default_strategies(),
+        )
+        .await
+        .unwrap();
+
+        // * Holding loses everything on bar 10 and has nothing left to gain on bar 11
+        let benchmark = &results["cdc"].benchmark;
+        assert!(benchmark[10] == dec!(-1));
+        assert!(benchmark[11] == Decimal::ZERO);
+        assert!(results["cdc"].get_summary().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_start_backtesting_with_benchmark() {
+        let mut source = recent_source();
+        let mut benchmark = mock_candles();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+        // * Flat benchmark covering the whole window
+        for (index, candle) in benchmark.iter_mut().enumerate() {
+            candle.timestamp = now - (40 - index as i64) * DAY_IN_MILLISECONDS;
+            candle.close = dec!(100);
+        }
+        source.insert("flat", benchmark);
+
+        let params = IBackTestingParams {
+            benchmark: Some("flat"),
+            ..IBackTestingParams::new("mock", 60)
+        };
+        let results = start_backtesting(&source, params, vec![Box::new(Threshold)])
+            .await
+            .unwrap();
+        let summary = results["threshold"].get_summary().await.unwrap();
+
+        assert!(summary.benchmark_return == Decimal::ZERO);
+        assert!(summary.excess_return.round_dp(10) == summary.returns.round_dp(10));
+
+        let params = IBackTestingParams {
+            benchmark: Some("unknown"),
+            ..IBackTestingParams::new("mock", 60)
+        };
+        assert!(
+            start_backtesting(&source, params, vec![Box::new(Threshold)])
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_backtesting_errors() {
+        let mut source = InMemorySource::new();
+        source.insert("mock", mock_candles());
+
+        // * Mock candles are from 2022, nothing falls into the last 5 days
+        let result = start_backtesting(
+            &source,
+            IBackTestingParams::new("mock", 5),
+            strategy::default_strategies(),
+        )
+        .await;
+        assert!(matches!(result, Err(MidasError::InsufficientData { .. })));
+
+        let result = start_backtesting(
+            &source,
+            IBackTestingParams::new("unknown", 5),
+            strategy::default_strategies(),
+        )
+        .await;
+        assert!(matches!(result, Err(MidasError::InvalidInput(_))));
+
+        let result = start_backtesting(
+            &recent_source(),
+            IBackTestingParams::new("mock", 60),
+            vec![Box::new(Threshold), Box::new(Threshold)],
+        )
+        .await;
+        assert!(matches!(result, Err(MidasError::InvalidInput(_))));
+    }
 }
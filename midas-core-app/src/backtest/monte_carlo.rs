@@ -0,0 +1,180 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::summary::IBackTestingSummary;
+use crate::error::MidasError;
+use crate::utils::calculate_maximum_drawdown;
+use crate::utils::random::SeededRng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResamplingMethod {
+    /// Same trades in a random order, only the path changes, not the final return
+    Shuffle,
+    /// Draw trades with replacement, some trades repeat and others are left out
+    Bootstrap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IMonteCarloParams {
+    pub simulations: usize,
+    pub method: ResamplingMethod,
+    pub seed: u64,
+    /// Two-sided confidence level of the intervals, e.g. 0.95
+    pub confidence: Decimal,
+}
+
+impl IMonteCarloParams {
+    pub fn new(seed: u64) -> IMonteCarloParams {
+        IMonteCarloParams {
+            simulations: 5000,
+            method: ResamplingMethod::Bootstrap,
+            seed,
+            confidence: Decimal::new(95, 2),
+        }
+    }
+}
+
+/// Distribution of one metric across the simulations
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IDistribution {
+    pub mean: Decimal,
+    pub median: Decimal,
+    pub min: Decimal,
+    pub max: Decimal,
+    /// Bounds of the confidence interval
+    pub lower: Decimal,
+    pub upper: Decimal,
+    /// Every simulated value, sorted
+    pub samples: Vec<Decimal>,
+}
+
+impl IDistribution {
+    fn new(mut samples: Vec<Decimal>, confidence: Decimal) -> IDistribution {
+        samples.sort();
+        let tail = (Decimal::ONE - confidence) / Decimal::TWO;
+        let length = Decimal::from(samples.len());
+        IDistribution {
+            mean: samples.iter().sum::<Decimal>() / length,
+            median: percentile(&samples, Decimal::new(5, 1)),
+            min: samples[0],
+            max: samples[samples.len() - 1],
+            lower: percentile(&samples, tail),
+            upper: percentile(&samples, Decimal::ONE - tail),
+            samples,
+        }
+    }
+    /// Value below which `share` of the simulations fall, e.g. 0.05 for the worst 5%
+    pub fn percentile(&self, share: Decimal) -> Decimal {
+        percentile(&self.samples, share)
+    }
+}
+
+/// Nearest rank percentile of sorted, non empty `samples`
+fn percentile(samples: &[Decimal], share: Decimal) -> Decimal {
+    let rank = (share.clamp(Decimal::ZERO, Decimal::ONE) * Decimal::from(samples.len() - 1))
+        .round()
+        .try_into()
+        .unwrap_or(0usize);
+    samples[rank.min(samples.len() - 1)]
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IMonteCarloResult {
+    /// Compounded return, every trade using the whole equity
+    pub final_return: IDistribution,
+    pub maximum_drawdown: IDistribution,
+    /// Longest losing streak, as counted by `IBackTestingSummary::get_max_consecutive_loss`
+    pub max_consec_lose: IDistribution,
+}
+
+/// Resample the trade `returns` `params.simulations` times
+pub fn monte_carlo(
+    returns: &[Decimal],
+    params: &IMonteCarloParams,
+) -> Result<IMonteCarloResult, MidasError> {
+    if returns.is_empty() || params.simulations == 0 {
+        return Err(MidasError::InsufficientData {
+            required: 1,
+            available: returns.len().min(params.simulations),
+        });
+    }
+    if params.confidence <= Decimal::ZERO || params.confidence >= Decimal::ONE {
+        return Err(MidasError::InvalidInput(format!(
+            "Confidence must be between 0 and 1, got {}",
+            params.confidence
+        )));
+    }
+    let mut rng = SeededRng::new(params.seed);
+    let mut final_return: Vec<Decimal> = Vec::with_capacity(params.simulations);
+    let mut maximum_drawdown: Vec<Decimal> = Vec::with_capacity(params.simulations);
+    let mut max_consec_lose: Vec<Decimal> = Vec::with_capacity(params.simulations);
+    let mut path: Vec<Decimal> = returns.to_vec();
+
+    for _ in 0..params.simulations {
+        match params.method {
+            ResamplingMethod::Shuffle => rng.shuffle(&mut path),
+            ResamplingMethod::Bootstrap => path
+                .iter_mut()
+                .for_each(|value| *value = returns[rng.next_below(returns.len())]),
+        }
+        let equity = IBackTestingSummary::get_compounded_equity(&path)?;
+        final_return.push(equity[equity.len() - 1] - Decimal::ONE);
+        maximum_drawdown.push(calculate_maximum_drawdown(equity)?.unwrap_or(Decimal::ZERO));
+        max_consec_lose.push(Decimal::from(
+            IBackTestingSummary::get_max_consecutive_loss(&path),
+        ));
+    }
+
+    Ok(IMonteCarloResult {
+        final_return: IDistribution::new(final_return, params.confidence),
+        maximum_drawdown: IDistribution::new(maximum_drawdown, params.confidence),
+        max_consec_lose: IDistribution::new(max_consec_lose, params.confidence),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_monte_carlo() {
+        let returns = [dec!(0.1), dec!(-0.05), dec!(0.2), dec!(-0.1), dec!(0.05)];
+        let params = IMonteCarloParams {
+            simulations: 500,
+            method: ResamplingMethod::Shuffle,
+            ..IMonteCarloParams::new(7)
+        };
+
+        let shuffled = monte_carlo(&returns, &params).unwrap();
+        // * Reordering never changes the compounded return
+        assert!(shuffled.final_return.min == shuffled.final_return.max);
+        assert!(shuffled.maximum_drawdown.min <= shuffled.maximum_drawdown.max);
+        assert!(shuffled.maximum_drawdown.max <= Decimal::ZERO);
+        // * Worst order is both losses in a row, 1.1 * 1.05 * 1.2 * 1.05 -> 0.95 * 0.9
+        assert!(shuffled.maximum_drawdown.min == dec!(-0.145));
+
+        let bootstrap = monte_carlo(
+            &returns,
+            &IMonteCarloParams {
+                simulations: 500,
+                ..IMonteCarloParams::new(7)
+            },
+        )
+        .unwrap();
+        let again = monte_carlo(
+            &returns,
+            &IMonteCarloParams {
+                simulations: 500,
+                ..IMonteCarloParams::new(7)
+            },
+        )
+        .unwrap();
+        assert!(bootstrap.final_return.samples == again.final_return.samples);
+        assert!(bootstrap.final_return.lower < bootstrap.final_return.upper);
+        assert!(bootstrap.final_return.percentile(dec!(0)) == bootstrap.final_return.min);
+        assert!(bootstrap.max_consec_lose.max >= Decimal::ONE);
+
+        assert!(monte_carlo(&[], &params).is_err());
+    }
+}
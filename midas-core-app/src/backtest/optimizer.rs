@@ -0,0 +1,366 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::engine::run_strategy;
+use super::metrics::IMetricsParams;
+use super::strategy::Strategy;
+use super::summary::IBackTestingSummary;
+use super::IBackTestingParams;
+use crate::error::MidasError;
+use crate::get_price::candle::PriceSeries;
+
+/// Values swept for one parameter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IParameterRange {
+    pub name: String,
+    pub values: Vec<Decimal>,
+}
+
+impl IParameterRange {
+    /// `start`, `start + step`, ... up to and including `end`
+    pub fn new(
+        name: &str,
+        start: Decimal,
+        end: Decimal,
+        step: Decimal,
+    ) -> Result<IParameterRange, MidasError> {
+        if step <= Decimal::ZERO {
+            return Err(MidasError::InvalidInput(format!(
+                "Step of {name} must be positive, got {step}"
+            )));
+        }
+        let mut values: Vec<Decimal> = Vec::new();
+        let mut value = start;
+        while value <= end {
+            values.push(value);
+            let Some(next) = value.checked_add(step) else {
+                break;
+            };
+            value = next;
+        }
+        Ok(IParameterRange {
+            name: name.to_string(),
+            values,
+        })
+    }
+}
+
+/// What the optimizer maximizes
+#[derive(Debug, Clone, Copy)]
+pub enum Objective {
+    Returns,
+    Sharpe,
+    Sortino,
+    Calmar,
+    /// Return over the absolute maximum drawdown, a run without drawdown scores its return
+    ReturnOverDrawdown,
+    ProfitFactor,
+    Custom(fn(&IBackTestingSummary) -> Decimal),
+}
+
+impl Objective {
+    pub fn score(&self, summary: &IBackTestingSummary) -> Decimal {
+        match self {
+            Objective::Returns => summary.returns,
+            Objective::Sharpe => summary.sharpe_ratio,
+            Objective::Sortino => summary.sortino_ratio,
+            Objective::Calmar => summary.calmar_ratio,
+            Objective::ReturnOverDrawdown => summary
+                .returns
+                .checked_div(summary.maximum_drawdown.abs())
+                .unwrap_or(summary.returns),
+            Objective::ProfitFactor => summary.profit_factor,
+            Objective::Custom(score) => score(summary),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IGridSearchParams {
+    pub ranges: Vec<IParameterRange>,
+    pub objective: Objective,
+    pub metrics: IMetricsParams,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IOptimizationRow {
+    /// One value per range, in the order of `IGridSearchParams::ranges`
+    pub parameters: Vec<Decimal>,
+    pub score: Decimal,
+    pub summary: IBackTestingSummary,
+}
+
+/// Score of the best run in every cell of a two parameter grid
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IHeatmap {
+    pub x_name: String,
+    pub y_name: String,
+    pub x: Vec<Decimal>,
+    pub y: Vec<Decimal>,
+    /// `scores[y][x]`, `None` when every run of the cell was skipped
+    pub scores: Vec<Vec<Option<Decimal>>>,
+}
+
+#[derive(Debug)]
+pub struct IOptimizationResult {
+    pub names: Vec<String>,
+    /// Best score first
+    pub rows: Vec<IOptimizationRow>,
+    /// Parameters that could not be backtested, e.g. a fast period longer than the slow one
+    pub skipped: Vec<(Vec<Decimal>, MidasError)>,
+}
+
+impl IOptimizationResult {
+    pub fn best(&self) -> Option<&IOptimizationRow> {
+        self.rows.first()
+    }
+    /// One line per run with its parameters, score and headline metrics
+    pub fn to_csv(&self) -> String {
+        let mut header: Vec<String> = self.names.clone();
+        header.extend(
+            [
+                "score",
+                "returns",
+                "sharpe_ratio",
+                "maximum_drawdown",
+                "total_trade",
+            ]
+            .map(String::from),
+        );
+        let mut lines: Vec<String> = vec![header.join(",")];
+        for row in &self.rows {
+            let mut cells: Vec<String> = row.parameters.iter().map(Decimal::to_string).collect();
+            cells.extend([
+                row.score.to_string(),
+                row.summary.returns.to_string(),
+                row.summary.sharpe_ratio.to_string(),
+                row.summary.maximum_drawdown.to_string(),
+                row.summary.total_trade.to_string(),
+            ]);
+            lines.push(cells.join(","));
+        }
+        lines.join("\n")
+    }
+    /// Pivot the grid on two parameters, other parameters keep their best score
+    pub fn heatmap(&self, x_name: &str, y_name: &str) -> Result<IHeatmap, MidasError> {
+        let position = |name: &str| {
+            self.names
+                .iter()
+                .position(|value| value == name)
+                .ok_or_else(|| MidasError::InvalidInput(format!("Unknown parameter {name}")))
+        };
+        let (x_index, y_index) = (position(x_name)?, position(y_name)?);
+        let axis = |index: usize| {
+            let mut values: Vec<Decimal> = self
+                .rows
+                .iter()
+                .map(|row| row.parameters[index])
+                .chain(self.skipped.iter().map(|(parameters, _)| parameters[index]))
+                .collect();
+            values.sort();
+            values.dedup();
+            values
+        };
+        let (x, y) = (axis(x_index), axis(y_index));
+        let mut scores: Vec<Vec<Option<Decimal>>> = vec![vec![None; x.len()]; y.len()];
+        for row in &self.rows {
+            let column = x
+                .binary_search(&row.parameters[x_index])
+                .unwrap_or_default();
+            let line = y
+                .binary_search(&row.parameters[y_index])
+                .unwrap_or_default();
+            let cell = &mut scores[line][column];
+            *cell = Some(cell.map_or(row.score, |score| score.max(row.score)));
+        }
+        Ok(IHeatmap {
+            x_name: x_name.to_string(),
+            y_name: y_name.to_string(),
+            x,
+            y,
+            scores,
+        })
+    }
+}
+
+/// Every combination of the range values
+fn get_grid(ranges: &[IParameterRange]) -> Vec<Vec<Decimal>> {
+    ranges.iter().fold(vec![Vec::new()], |grid, range| {
+        grid.iter()
+            .flat_map(|parameters| {
+                range.values.iter().map(move |value| {
+                    let mut parameters = parameters.clone();
+                    parameters.push(*value);
+                    parameters
+                })
+            })
+            .collect()
+    })
+}
+
+/// Backtest every combination of `search.ranges` on `series`, spread over the available cores
+/// `build` turns one combination into a strategy and may adjust its copy of `params`, e.g. the exits
+pub fn grid_search<F>(
+    series: &PriceSeries,
+    params: &IBackTestingParams,
+    search: &IGridSearchParams,
+    build: F,
+) -> Result<IOptimizationResult, MidasError>
+where
+    F: Fn(&[Decimal], &mut IBackTestingParams) -> Result<Box<dyn Strategy>, MidasError> + Sync,
+{
+    let grid = get_grid(&search.ranges);
+    let next = AtomicUsize::new(0);
+    let workers = thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(1)
+        .min(grid.len());
+
+    let run = |parameters: &[Decimal]| -> Result<IOptimizationRow, MidasError> {
+        let mut params = params.clone();
+        let mut strategy = build(parameters, &mut params)?;
+        let result = run_strategy(strategy.as_mut(), series, &params)?;
+        let summary = IBackTestingSummary::calculate_with_metrics(&result, &search.metrics)?;
+        Ok(IOptimizationRow {
+            parameters: parameters.to_vec(),
+            score: search.objective.score(&summary),
+            summary,
+        })
+    };
+    // * A panicking strategy only skips its own combination
+    let outcomes = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut outcomes = Vec::new();
+                    while let Some(parameters) = grid.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let outcome = panic::catch_unwind(AssertUnwindSafe(|| run(parameters)))
+                            .unwrap_or_else(|_| {
+                                Err(MidasError::InvalidInput(format!(
+                                    "Backtest of {parameters:?} panicked"
+                                )))
+                            });
+                        outcomes.push((parameters.clone(), outcome));
+                    }
+                    outcomes
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join())
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .map_err(|_| MidasError::InvalidInput("Optimizer worker panicked".to_string()))?;
+
+    let mut rows: Vec<IOptimizationRow> = Vec::new();
+    let mut skipped: Vec<(Vec<Decimal>, MidasError)> = Vec::new();
+    for (parameters, outcome) in outcomes.into_iter().flatten() {
+        match outcome {
+            Ok(row) => rows.push(row),
+            Err(error) => skipped.push((parameters, error)),
+        }
+    }
+    rows.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.parameters.cmp(&b.parameters))
+    });
+    skipped.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(IOptimizationResult {
+        names: search
+            .ranges
+            .iter()
+            .map(|range| range.name.clone())
+            .collect(),
+        rows,
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::mock::{mock_candles, mock_ma_cross};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_grid_search() {
+        let series = PriceSeries::new(mock_candles());
+        let search = IGridSearchParams {
+            ranges: vec![
+                IParameterRange::new("fast", dec!(2), dec!(6), dec!(2)).unwrap(),
+                IParameterRange::new("slow", dec!(5), dec!(10), dec!(5)).unwrap(),
+            ],
+            objective: Objective::Returns,
+            metrics: IMetricsParams::new(),
+        };
+
+        let result = grid_search(
+            &series,
+            &IBackTestingParams::new("mock", 30),
+            &search,
+            mock_ma_cross,
+        )
+        .unwrap();
+
+        // * 6 / 5 is skipped, fast is longer than slow
+        assert!(result.rows.len() == 5);
+        assert!(result.skipped.len() == 1);
+        assert!(result.skipped[0].0 == vec![dec!(6), dec!(5)]);
+        assert!(result
+            .rows
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+        assert!(result.best().unwrap().score == result.rows[0].summary.returns);
+
+        let csv = result.to_csv();
+        assert!(csv.starts_with("fast,slow,score,"));
+        assert!(csv.lines().count() == 6);
+
+        let heatmap = result.heatmap("fast", "slow").unwrap();
+        assert!(heatmap.x == vec![dec!(2), dec!(4), dec!(6)]);
+        assert!(heatmap.y == vec![dec!(5), dec!(10)]);
+        assert!(heatmap.scores[0][2].is_none());
+        assert!(heatmap.scores[1][2].is_some());
+        assert!(result.heatmap("fast", "stop").is_err());
+        // * Stops at the last value a Decimal holds
+        let range = IParameterRange::new("near_max", Decimal::MAX - dec!(1), Decimal::MAX, dec!(1));
+        assert!(range.unwrap().values.len() == 2);
+    }
+
+    #[test]
+    fn test_grid_search_skips_panics() {
+        let series = PriceSeries::new(mock_candles());
+        let search = IGridSearchParams {
+            ranges: vec![
+                IParameterRange::new("fast", dec!(2), dec!(3), dec!(1)).unwrap(),
+                IParameterRange::new("slow", dec!(5), dec!(5), dec!(1)).unwrap(),
+            ],
+            objective: Objective::Returns,
+            metrics: IMetricsParams::new(),
+        };
+        let build = |parameters: &[Decimal], params: &mut IBackTestingParams| {
+            if parameters[0] == dec!(3) {
+                panic!("unsupported fast period");
+            }
+            mock_ma_cross(parameters, params)
+        };
+
+        let result = grid_search(
+            &series,
+            &IBackTestingParams::new("mock", 30),
+            &search,
+            build,
+        )
+        .unwrap();
+
+        assert!(result.rows.len() == 1);
+        assert!(result.skipped[0].0 == vec![dec!(3), dec!(5)]);
+        assert!(matches!(result.skipped[0].1, MidasError::InvalidInput(_)));
+    }
+}
@@ -0,0 +1,628 @@
+use std::collections::BTreeSet;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::metrics::IMetricsParams;
+use super::strategy::{Signal, Strategy};
+use super::summary::IBackTestingSummary;
+use super::{
+    get_window, IBackTestingParams, IBackTestingResult, IBackTestingSingleResult, IEquityPoint,
+    POSITION,
+};
+use crate::error::MidasError;
+use crate::get_price::candle::{Candle, PriceSeries};
+use crate::get_price::source::PriceSource;
+use crate::risk_management::exit_levels::ExitReason;
+
+/// How equity is split between the assets of the basket
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Allocation {
+    /// `1 / n` of equity per asset, flat assets leave their share in cash
+    EqualWeight,
+    /// One weight per asset summing to at most 1, flat assets leave their share in cash
+    Fixed(Vec<Decimal>),
+    /// Equity split equally between the assets holding a position
+    SignalWeighted,
+}
+
+/// When positions are traded back to their target weights
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rebalance {
+    /// Only when a signal opens or closes a position
+    OnSignal,
+    /// Also every `n` bars of the combined timeline
+    Periodic(usize),
+    /// Also when an asset drifts more than this from its target weight, e.g. 0.05
+    Threshold(Decimal),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IPortfolioParams {
+    pub allocation: Allocation,
+    pub rebalance: Rebalance,
+}
+
+/// One coin of the basket with the strategy trading it
+pub struct IPortfolioAsset {
+    pub coin_id: String,
+    pub series: PriceSeries,
+    pub strategy: Box<dyn Strategy>,
+}
+
+/// Order filled for one asset, `quantity` is negative for a sell
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct IPortfolioOrder {
+    /// Index of the asset in the basket
+    pub asset: usize,
+    pub timestamp: i64,
+    /// Bar of the combined timeline
+    pub index: usize,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub fill_price: Decimal,
+    pub fee: Decimal,
+    /// Resizes a position that stays open
+    pub rebalance: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IPortfolioResult {
+    pub coins: Vec<String>,
+    /// Every timestamp of every asset, the bars of the combined timeline
+    pub timestamps: Vec<i64>,
+    pub orders: Vec<IPortfolioOrder>,
+    /// Weight of every asset at the close of every bar, `weights[bar][asset]`, negative for a short
+    pub weights: Vec<Vec<Decimal>>,
+    /// Round trips of every asset, each entry directly followed by its exit, with the combined
+    /// equity curve and an equally weighted basket as benchmark
+    /// Buying more moves an entry to the average cost, selling part books its own round trip,
+    /// curve points hold the net position value as quantity
+    pub combined: IBackTestingResult<Decimal>,
+}
+
+impl IPortfolioResult {
+    pub fn get_summary(&self, metrics: &IMetricsParams) -> Result<IBackTestingSummary, MidasError> {
+        IBackTestingSummary::calculate_with_metrics(&self.combined, metrics)
+    }
+}
+
+/// Target weight of every asset from its direction, `1` long, `-1` short, `0` flat
+fn get_target_weights(
+    directions: &[Decimal],
+    allocation: &Allocation,
+) -> Result<Vec<Decimal>, MidasError> {
+    match allocation {
+        Allocation::EqualWeight => {
+            let share = Decimal::ONE / Decimal::from(directions.len());
+            Ok(directions
+                .iter()
+                .map(|direction| direction * share)
+                .collect())
+        }
+        Allocation::Fixed(weights) => {
+            if weights.len() != directions.len() {
+                return Err(MidasError::InvalidInput(format!(
+                    "{} weights for {} assets",
+                    weights.len(),
+                    directions.len()
+                )));
+            }
+            if weights.iter().any(|weight| weight.is_sign_negative())
+                || weights.iter().sum::<Decimal>() > Decimal::ONE
+            {
+                return Err(MidasError::InvalidInput(
+                    "Weights must be positive and sum to at most 1".to_string(),
+                ));
+            }
+            Ok(directions.iter().zip(weights).map(|(d, w)| d * w).collect())
+        }
+        Allocation::SignalWeighted => {
+            let active = directions.iter().filter(|d| !d.is_zero()).count();
+            let share = Decimal::ONE
+                .checked_div(Decimal::from(active))
+                .unwrap_or(Decimal::ZERO);
+            Ok(directions
+                .iter()
+                .map(|direction| direction * share)
+                .collect())
+        }
+    }
+}
+
+type RoundTrip = (
+    IBackTestingSingleResult<Decimal>,
+    IBackTestingSingleResult<Decimal>,
+);
+
+/// Cash, holdings and open round trips of the basket
+struct Book<'a, 'b> {
+    params: &'a IBackTestingParams<'b>,
+    cash: Decimal,
+    quantities: Vec<Decimal>,
+    entries: Vec<Option<IBackTestingSingleResult<Decimal>>>,
+    /// Closed round trips as `(entry, exit)`
+    trades: Vec<RoundTrip>,
+    orders: Vec<IPortfolioOrder>,
+}
+
+impl Book<'_, '_> {
+    /// Buy (positive `quantity`) or sell at the close of `candle`
+    fn fill(&mut self, asset: usize, quantity: Decimal, candle: &Candle, index: usize) {
+        let costs = &self.params.costs;
+        let price = candle.close;
+        let is_buy = quantity > Decimal::ZERO;
+        let fill_price = costs.fill_price(price, is_buy, candle, (quantity * price).abs());
+        let fee = costs.fee(quantity * fill_price);
+        self.cash -= quantity * fill_price + fee;
+
+        let before = self.quantities[asset];
+        let after = before + quantity;
+        self.quantities[asset] = after;
+        let flips = before.is_sign_negative() != after.is_sign_negative();
+        let closes = !before.is_zero() && (after.is_zero() || flips);
+        let opens = !after.is_zero() && (before.is_zero() || flips);
+        self.orders.push(IPortfolioOrder {
+            asset,
+            timestamp: candle.timestamp,
+            index,
+            quantity,
+            price,
+            fill_price,
+            fee,
+            rebalance: !closes && !opens,
+        });
+
+        let template = IBackTestingSingleResult {
+            action: POSITION::Long,
+            price,
+            timestamp: candle.timestamp,
+            index,
+            fill_price,
+            quantity: Decimal::ZERO,
+            fee,
+            exit_reason: None,
+        };
+        if let (false, false, Some(entry)) = (closes, opens, self.entries[asset].as_mut()) {
+            let held = before.abs();
+            let traded = quantity.abs();
+            if after.abs() > held {
+                // * Adding to the position moves the entry to the weighted average cost
+                entry.price = (entry.price * held + price * traded) / after.abs();
+                entry.fill_price = (entry.fill_price * held + fill_price * traded) / after.abs();
+                entry.quantity = after.abs();
+                entry.fee += fee;
+            } else {
+                // * Trimming the position realizes that part as its own round trip
+                let share = entry.fee * traded / held;
+                let is_long = entry.action == POSITION::Long;
+                self.trades.push((
+                    IBackTestingSingleResult {
+                        quantity: traded,
+                        fee: share,
+                        ..*entry
+                    },
+                    IBackTestingSingleResult {
+                        action: if is_long {
+                            POSITION::Close
+                        } else {
+                            POSITION::Cover
+                        },
+                        quantity: traded,
+                        exit_reason: Some(ExitReason::Signal),
+                        ..template
+                    },
+                ));
+                entry.quantity = after.abs();
+                entry.fee -= share;
+            }
+        }
+        if closes {
+            if let Some(entry) = self.entries[asset].take() {
+                let is_long = entry.action == POSITION::Long;
+                self.trades.push((
+                    entry,
+                    IBackTestingSingleResult {
+                        action: if is_long {
+                            POSITION::Close
+                        } else {
+                            POSITION::Cover
+                        },
+                        quantity: before.abs(),
+                        // * A flip pays one fee, shared pro rata with the new position
+                        fee: fee * before.abs() / quantity.abs(),
+                        exit_reason: Some(ExitReason::Signal),
+                        ..template
+                    },
+                ));
+            }
+        }
+        if opens {
+            self.entries[asset] = Some(IBackTestingSingleResult {
+                action: if after > Decimal::ZERO {
+                    POSITION::Long
+                } else {
+                    POSITION::Short
+                },
+                quantity: after.abs(),
+                fee: fee * after.abs() / quantity.abs(),
+                ..template
+            });
+        }
+    }
+}
+
+/// Run every asset with its own strategy on shared capital, filled at the close of each bar
+/// Bars of all assets are merged by timestamp, an asset is only traded on its own bars and valued
+/// at its last close in between
+/// `params` gives the capital, costs and `allow_short`, its exits and sizing are not used
+pub fn backtest_portfolio(
+    assets: &mut [IPortfolioAsset],
+    params: &IBackTestingParams,
+    portfolio: &IPortfolioParams,
+) -> Result<IPortfolioResult, MidasError> {
+    if assets.is_empty() {
+        return Err(MidasError::InvalidInput(
+            "Portfolio needs at least one asset".to_string(),
+        ));
+    }
+    match portfolio.rebalance {
+        Rebalance::Periodic(0) => {
+            return Err(MidasError::InvalidInput(
+                "Rebalancing period must be at least one bar".to_string(),
+            ))
+        }
+        Rebalance::Threshold(threshold) if threshold.is_sign_negative() => {
+            return Err(MidasError::InvalidInput(format!(
+                "Rebalancing threshold must be positive, got {threshold}"
+            )))
+        }
+        _ => (),
+    }
+    let signals: Vec<Vec<Option<Signal>>> = assets
+        .iter_mut()
+        .map(|asset| asset.strategy.signals(&asset.series))
+        .collect::<Result<Vec<Vec<Option<Signal>>>, MidasError>>()?;
+    let timestamps: Vec<i64> = assets
+        .iter()
+        .flat_map(|asset| asset.series.timestamps())
+        .collect::<BTreeSet<i64>>()
+        .into_iter()
+        .collect();
+
+    let count = assets.len();
+    let mut book = Book {
+        params,
+        cash: params.initial_capital,
+        quantities: vec![Decimal::ZERO; count],
+        entries: vec![None; count],
+        trades: Vec::new(),
+        orders: Vec::new(),
+    };
+    let mut cursors: Vec<usize> = vec![0; count];
+    let mut prices: Vec<Option<Decimal>> = vec![None; count];
+    let mut directions: Vec<Decimal> = vec![Decimal::ZERO; count];
+    let mut weights: Vec<Vec<Decimal>> = Vec::with_capacity(timestamps.len());
+    let mut equity_curve: Vec<IEquityPoint<Decimal>> = Vec::with_capacity(timestamps.len());
+    let mut benchmark: Vec<Decimal> = Vec::with_capacity(timestamps.len());
+    let mut peak = params.initial_capital;
+
+    for (index, timestamp) in timestamps.iter().enumerate() {
+        // * Move every asset with a bar at this timestamp and apply its signal
+        let mut bars: Vec<Option<Candle>> = vec![None; count];
+        let mut changed = false;
+        let mut basket_return = Decimal::ZERO;
+        let mut priced = 0;
+        for asset in 0..count {
+            let candles = assets[asset].series.candles();
+            let Some(candle) = candles.get(cursors[asset]) else {
+                continue;
+            };
+            if candle.timestamp != *timestamp {
+                continue;
+            }
+            if let Some(previous) = prices[asset] {
+                basket_return += (candle.close - previous)
+                    .checked_div(previous)
+                    .unwrap_or(Decimal::ZERO);
+                priced += 1;
+            }
+            let direction = match signals[asset].get(cursors[asset]).copied().flatten() {
+                Some(Signal::Long) => Decimal::ONE,
+                Some(Signal::Short) if params.allow_short => Decimal::NEGATIVE_ONE,
+                Some(Signal::Short) | Some(Signal::Close) => Decimal::ZERO,
+                None => directions[asset],
+            };
+            changed |= direction != directions[asset];
+            directions[asset] = direction;
+            prices[asset] = Some(candle.close);
+            bars[asset] = Some(*candle);
+            cursors[asset] += 1;
+        }
+        benchmark.push(if index == 0 || priced == 0 {
+            Decimal::ZERO
+        } else {
+            basket_return / Decimal::from(priced)
+        });
+
+        let value = |book: &Book, asset: usize| {
+            book.quantities[asset] * prices[asset].unwrap_or(Decimal::ZERO)
+        };
+        let equity = book.cash + (0..count).map(|asset| value(&book, asset)).sum::<Decimal>();
+        let targets = get_target_weights(&directions, &portfolio.allocation)?;
+        let due = changed
+            || match portfolio.rebalance {
+                Rebalance::OnSignal => false,
+                Rebalance::Periodic(period) => index % period == 0,
+                Rebalance::Threshold(threshold) => (0..count).any(|asset| {
+                    let weight = value(&book, asset)
+                        .checked_div(equity)
+                        .unwrap_or(Decimal::ZERO);
+                    (weight - targets[asset]).abs() > threshold
+                }),
+            };
+
+        if due && equity > Decimal::ZERO {
+            let mut orders: Vec<(usize, Decimal)> = (0..count)
+                .filter(|asset| bars[*asset].is_some())
+                .map(|asset| {
+                    let price = prices[asset].unwrap_or(Decimal::ONE);
+                    let target = targets[asset] * equity / price;
+                    (asset, target - book.quantities[asset])
+                })
+                .filter(|(_, quantity)| !quantity.is_zero())
+                .collect();
+            // * Sell first so the buys can use the cash
+            orders.sort_by_key(|(_, quantity)| *quantity);
+            for (asset, mut quantity) in orders {
+                let Some(candle) = bars[asset] else {
+                    continue;
+                };
+                if quantity > Decimal::ZERO {
+                    let costs = &params.costs;
+                    let fill_price =
+                        costs.fill_price(candle.close, true, &candle, quantity * candle.close);
+                    let affordable = ((book.cash - costs.fixed_fee)
+                        / (fill_price * (Decimal::ONE + costs.fee_rate())))
+                    .max(Decimal::ZERO);
+                    quantity = quantity.min(affordable);
+                    if quantity.is_zero() {
+                        continue;
+                    }
+                }
+                book.fill(asset, quantity, &candle, index);
+            }
+        }
+
+        let position_value: Decimal = (0..count).map(|asset| value(&book, asset)).sum();
+        let point = IEquityPoint::new(
+            *timestamp,
+            index,
+            book.cash,
+            position_value,
+            Decimal::ONE,
+            peak,
+        )?;
+        peak = peak.max(point.equity);
+        weights.push(
+            (0..count)
+                .map(|asset| {
+                    value(&book, asset)
+                        .checked_div(point.equity)
+                        .unwrap_or(Decimal::ZERO)
+                })
+                .collect(),
+        );
+        equity_curve.push(point);
+    }
+
+    // * Round trips in the order they closed, positions still open at the end come last
+    let Book {
+        mut trades,
+        entries,
+        orders,
+        ..
+    } = book;
+    trades.sort_by_key(|(_, exit)| exit.index);
+    let mut result: Vec<IBackTestingSingleResult<Decimal>> = trades
+        .into_iter()
+        .flat_map(|(entry, exit)| [entry, exit])
+        .collect();
+    result.extend(entries.into_iter().flatten());
+
+    Ok(IPortfolioResult {
+        coins: assets.iter().map(|asset| asset.coin_id.clone()).collect(),
+        timestamps,
+        orders,
+        weights,
+        combined: IBackTestingResult {
+            result,
+            equity_curve,
+            initial_capital: params.initial_capital,
+            benchmark,
+            open_position: None,
+        },
+    })
+}
+
+/// Fetch `params.period` days of every coin from `source` and backtest them as one portfolio
+pub async fn start_portfolio_backtesting<S: PriceSource>(
+    source: &S,
+    params: &IBackTestingParams<'_>,
+    portfolio: &IPortfolioParams,
+    strategies: Vec<(&str, Box<dyn Strategy>)>,
+) -> Result<IPortfolioResult, MidasError> {
+    let (from, to) = get_window(params.period)?;
+    let mut assets: Vec<IPortfolioAsset> = Vec::with_capacity(strategies.len());
+    for (coin_id, strategy) in strategies {
+        assets.push(IPortfolioAsset {
+            coin_id: coin_id.to_string(),
+            series: source.get_candles(coin_id, from, to).await?,
+            strategy,
+        });
+    }
+    backtest_portfolio(&mut assets, params, portfolio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::mock::mock_candles;
+    use rust_decimal_macros::dec;
+
+    /// Long from bar `start` until the end
+    struct HoldFrom(usize);
+
+    impl Strategy for HoldFrom {
+        fn name(&self) -> String {
+            "hold".to_string()
+        }
+        fn next(&mut self, bars: &[Candle]) -> Result<Option<Signal>, MidasError> {
+            Ok((bars.len() == self.0 + 1).then_some(Signal::Long))
+        }
+    }
+
+    /// Mock candles and a flat coin at 10 on the same days
+    fn basket(start: usize) -> Vec<IPortfolioAsset> {
+        let flat = mock_candles()
+            .iter()
+            .map(|candle| Candle::from_price(candle.timestamp, dec!(10), dec!(1)))
+            .collect();
+        vec![
+            IPortfolioAsset {
+                coin_id: "mock".to_string(),
+                series: PriceSeries::new(mock_candles()),
+                strategy: Box::new(HoldFrom(0)),
+            },
+            IPortfolioAsset {
+                coin_id: "flat".to_string(),
+                series: PriceSeries::new(flat),
+                strategy: Box::new(HoldFrom(start)),
+            },
+        ]
+    }
+
+    fn portfolio(allocation: Allocation, rebalance: Rebalance) -> IPortfolioParams {
+        IPortfolioParams {
+            allocation,
+            rebalance,
+        }
+    }
+
+    #[test]
+    fn test_backtest_portfolio_equal_weight() {
+        let params = IBackTestingParams::new("basket", 30);
+        let result = backtest_portfolio(
+            &mut basket(0),
+            &params,
+            &portfolio(Allocation::EqualWeight, Rebalance::OnSignal),
+        )
+        .unwrap();
+
+        // * 2500 coins bought at 2 are worth 24.12 each, the flat half stays at 5000
+        let last = result.combined.equity_curve.last().unwrap();
+        assert!(last.equity == dec!(65300));
+        assert!(result.timestamps.len() == 30);
+        assert!(result.orders.len() == 2);
+        assert!(result.weights[0] == vec![dec!(0.5), dec!(0.5)]);
+        assert!(result.combined.result.len() == 2);
+        assert!(result.combined.benchmark[0] == Decimal::ZERO);
+
+        let rebalanced = backtest_portfolio(
+            &mut basket(0),
+            &params,
+            &portfolio(Allocation::EqualWeight, Rebalance::Periodic(1)),
+        )
+        .unwrap();
+        assert!(rebalanced
+            .weights
+            .iter()
+            .all(|weights| weights == &vec![dec!(0.5), dec!(0.5)]));
+        assert!(rebalanced
+            .orders
+            .iter()
+            .skip(2)
+            .all(|order| order.rebalance));
+
+        let threshold = backtest_portfolio(
+            &mut basket(0),
+            &params,
+            &portfolio(Allocation::EqualWeight, Rebalance::Threshold(dec!(0.1))),
+        )
+        .unwrap();
+        assert!(threshold.orders.len() > 2);
+        assert!(threshold.orders.len() < rebalanced.orders.len());
+        assert!(threshold
+            .weights
+            .iter()
+            .all(|weights| (weights[0] - dec!(0.5)).abs() <= dec!(0.1)));
+    }
+
+    #[test]
+    fn test_backtest_portfolio_signal_weighted() {
+        let params = IBackTestingParams::new("basket", 30);
+        let result = backtest_portfolio(
+            &mut basket(5),
+            &params,
+            &portfolio(Allocation::SignalWeighted, Rebalance::OnSignal),
+        )
+        .unwrap();
+
+        // * Fully in the first coin until the second one signals on bar 5
+        assert!(result.weights[4] == vec![dec!(1), dec!(0)]);
+        assert!(result.weights[5] == vec![dec!(0.5), dec!(0.5)]);
+        assert!(result.orders[1].rebalance && !result.orders[2].rebalance);
+        assert!(result.get_summary(&IMetricsParams::new()).is_ok());
+
+        let fixed = backtest_portfolio(
+            &mut basket(5),
+            &params,
+            &portfolio(Allocation::Fixed(vec![dec!(0.3)]), Rebalance::OnSignal),
+        );
+        assert!(fixed.is_err());
+        let fixed = backtest_portfolio(
+            &mut basket(5),
+            &params,
+            &portfolio(
+                Allocation::Fixed(vec![dec!(0.3), dec!(0.2)]),
+                Rebalance::OnSignal,
+            ),
+        )
+        .unwrap();
+        assert!(fixed.weights[0] == vec![dec!(0.3), dec!(0)]);
+        assert!(fixed.combined.equity_curve[0].cash == dec!(7000));
+    }
+
+    #[test]
+    fn test_book_resizes() {
+        let params = IBackTestingParams::new("basket", 30);
+        let mut book = Book {
+            params: &params,
+            cash: params.initial_capital,
+            quantities: vec![Decimal::ZERO],
+            entries: vec![None],
+            trades: Vec::new(),
+            orders: Vec::new(),
+        };
+        let pnl = |(entry, exit): &RoundTrip| {
+            ((exit.fill_price - entry.fill_price) * exit.quantity).round_dp(10)
+        };
+
+        // * 10 at 1 and 5 at 2 cost 20, all 15 sold at 3 for 45
+        book.fill(0, dec!(10), &Candle::from_price(0, dec!(1), dec!(1)), 0);
+        book.fill(0, dec!(5), &Candle::from_price(1, dec!(2), dec!(1)), 1);
+        book.fill(0, dec!(-15), &Candle::from_price(2, dec!(3), dec!(1)), 2);
+        assert!(book.trades.len() == 1);
+        assert!(book.trades[0].1.quantity == dec!(15));
+        assert!(pnl(&book.trades[0]) == dec!(25));
+
+        // * 4 of 10 sold at 2 and the other 6 at 3
+        book.fill(0, dec!(10), &Candle::from_price(3, dec!(1), dec!(1)), 3);
+        book.fill(0, dec!(-4), &Candle::from_price(4, dec!(2), dec!(1)), 4);
+        book.fill(0, dec!(-6), &Candle::from_price(5, dec!(3), dec!(1)), 5);
+        assert!(book.trades.len() == 3);
+        assert!(pnl(&book.trades[1]) == dec!(4));
+        assert!(pnl(&book.trades[2]) == dec!(12));
+        assert!(book.cash == params.initial_capital + dec!(41));
+    }
+}
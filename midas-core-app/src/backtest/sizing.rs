@@ -0,0 +1,140 @@
+use rust_decimal::Decimal;
+
+use super::costs::ICostModel;
+use super::summary::IBackTestingSummary;
+use crate::error::MidasError;
+use crate::price_manipulation::returns::calculate_returns;
+use crate::price_manipulation::risks::calculate_volatility;
+use crate::risk_management::risk_reward_ratio::calculate_optimal_quantity;
+
+/// How much of the equity a new position takes, positions are never leveraged
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionSizing {
+    /// Whole equity on every trade
+    #[default]
+    AllIn,
+    /// Fraction of equity, e.g. 0.25 for a quarter
+    FixedFraction(Decimal),
+    /// Fraction of equity lost if the stop-loss of `IBackTestingParams::exits` is hit
+    FixedRisk(Decimal),
+    /// `scale` times the Kelly fraction, `scale` of 0.5 is the usual half Kelly
+    Kelly {
+        win_rate: Decimal,
+        win_loss_ratio: Decimal,
+        scale: Decimal,
+    },
+    /// Fraction of equity that makes the position's per bar volatility `target`,
+    /// estimated from the returns of the last `lookback` bars
+    VolatilityTarget { target: Decimal, lookback: usize },
+}
+
+/// Kelly fraction `W - (1 - W) / R`, clamped to `[0, 1]`
+pub fn kelly_fraction(win_rate: Decimal, win_loss_ratio: Decimal) -> Result<Decimal, MidasError> {
+    if win_loss_ratio <= Decimal::ZERO {
+        return Err(MidasError::InvalidInput(format!(
+            "Kelly needs a positive win / loss ratio, got {win_loss_ratio}"
+        )));
+    }
+    let fraction = win_rate - (Decimal::ONE - win_rate) / win_loss_ratio;
+    Ok(fraction.clamp(Decimal::ZERO, Decimal::ONE))
+}
+
+impl PositionSizing {
+    /// Kelly sizing from the win rate and win / loss ratio of a previous backtest
+    pub fn kelly_from_summary(summary: &IBackTestingSummary, scale: Decimal) -> PositionSizing {
+        PositionSizing::Kelly {
+            win_rate: summary.percent_profitable,
+            win_loss_ratio: summary.avg_win_loss_ratio,
+            scale,
+        }
+    }
+    /// Quantity to open at `fill_price` with `equity`, leaving enough cash for the fees
+    /// `closes` holds every close up to the entry bar, `stop_loss` is the stop price of the new position
+    /// A zero quantity means the trade is skipped, e.g. before `lookback` bars of volatility are available
+    pub fn quantity(
+        &self,
+        equity: Decimal,
+        fill_price: Decimal,
+        stop_loss: Option<Decimal>,
+        closes: &[Decimal],
+        costs: &ICostModel,
+    ) -> Result<Decimal, MidasError> {
+        let all_in = (equity - costs.fixed_fee)
+            .checked_div(fill_price * (Decimal::ONE + costs.fee_rate()))
+            .ok_or_else(|| MidasError::ArithmeticOverflow("position size".to_string()))?;
+        let quantity = match *self {
+            PositionSizing::AllIn => all_in,
+            PositionSizing::FixedFraction(fraction) => all_in * fraction,
+            PositionSizing::FixedRisk(risk) => {
+                let stop_loss = stop_loss.ok_or_else(|| {
+                    MidasError::InvalidInput("Fixed risk sizing needs a stop-loss".to_string())
+                })?;
+                if stop_loss == fill_price {
+                    return Err(MidasError::InvalidInput(
+                        "Stop-loss is at the entry price".to_string(),
+                    ));
+                }
+                calculate_optimal_quantity(equity * risk, fill_price, stop_loss).abs()
+            }
+            PositionSizing::Kelly {
+                win_rate,
+                win_loss_ratio,
+                scale,
+            } => all_in * kelly_fraction(win_rate, win_loss_ratio)? * scale,
+            PositionSizing::VolatilityTarget { target, lookback } => {
+                if lookback < 2 || closes.len() <= lookback {
+                    return Ok(Decimal::ZERO);
+                }
+                let returns = calculate_returns(&closes[closes.len() - lookback - 1..])?;
+                let volatility = calculate_volatility(&returns)?;
+                all_in
+                    * target
+                        .checked_div(volatility)
+                        .unwrap_or(Decimal::ONE)
+                        .min(Decimal::ONE)
+            }
+        };
+        Ok(quantity.clamp(Decimal::ZERO, all_in.max(Decimal::ZERO)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_position_sizing() {
+        let costs = ICostModel::new();
+        let closes = [dec!(100), dec!(110), dec!(99), dec!(108.9)];
+        let quantity = |sizing: PositionSizing, stop_loss: Option<Decimal>| {
+            sizing
+                .quantity(dec!(1000), dec!(10), stop_loss, &closes, &costs)
+                .unwrap()
+        };
+
+        assert!(quantity(PositionSizing::AllIn, None) == dec!(100));
+        assert!(quantity(PositionSizing::FixedFraction(dec!(0.25)), None) == dec!(25));
+        // * Risk 10 with a stop 0.5 away from the entry
+        assert!(quantity(PositionSizing::FixedRisk(dec!(0.01)), Some(dec!(9.5))) == dec!(20));
+        // * Never more than the equity
+        assert!(quantity(PositionSizing::FixedRisk(dec!(0.5)), Some(dec!(9.5))) == dec!(100));
+        // * 0.6 - 0.4 / 2 = 0.4, halved
+        let kelly = PositionSizing::Kelly {
+            win_rate: dec!(0.6),
+            win_loss_ratio: dec!(2),
+            scale: dec!(0.5),
+        };
+        assert!(quantity(kelly, None) == dec!(20));
+        // * Returns alternate between 10% and -10%, so the volatility is 10%
+        let volatility = PositionSizing::VolatilityTarget {
+            target: dec!(0.05),
+            lookback: 2,
+        };
+        assert!(quantity(volatility, None) == dec!(50));
+
+        assert!(PositionSizing::FixedRisk(dec!(0.01))
+            .quantity(dec!(1000), dec!(10), None, &closes, &costs)
+            .is_err());
+    }
+}
@@ -0,0 +1,490 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::error::MidasError;
+use crate::get_price::candle::{Candle, PriceSeries};
+use crate::ta_rs::bands::{
+    get_band_signals, get_bollinger_bands, get_donchian_channels, get_keltner_channels, BandSignal,
+    IBands,
+};
+use crate::ta_rs::cdc_action_zone::{
+    get_cdc_action_zone_with_periods, get_cdc_action_zone_with_zones, CdcAction,
+    ICdcActionZoneParams,
+};
+use crate::ta_rs::ma::get_ma_cross;
+use crate::ta_rs::macd::{get_macd, get_macd_line, IMACDParams};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Signal {
+    Long,
+    Short,
+    Close,
+}
+
+/// Trading rule that turns bars into signals
+///
+/// Implement `next` to receive bars one at a time, or override `signals` to work on the whole series
+/// at once (e.g. to reuse the batch indicators in `ta_rs`).
+pub trait Strategy: Send {
+    /// Key of this strategy in the backtest result
+    fn name(&self) -> String;
+    /// Signal on the last bar of `bars`, which holds every bar up to and including the current one
+    fn next(&mut self, bars: &[Candle]) -> Result<Option<Signal>, MidasError>;
+    /// Signals aligned with the bars of `series`
+    fn signals(&mut self, series: &PriceSeries) -> Result<Vec<Option<Signal>>, MidasError> {
+        let candles = series.candles();
+        (1..=candles.len())
+            .map(|end| self.next(&candles[..end]))
+            .collect::<Result<Vec<Option<Signal>>, MidasError>>()
+    }
+}
+
+/// Long when `indicator` crosses above zero, short when it crosses below
+/// `gap` is the indicator warm up, i.e. `indicator[0]` belongs to bar `gap`
+pub fn cross_signals(indicator: &[Decimal], gap: usize, length: usize) -> Vec<Option<Signal>> {
+    let mut signals: Vec<Option<Signal>> = vec![None; length];
+    for index in 1..indicator.len() {
+        if index + gap >= length {
+            break;
+        }
+        if indicator[index] > Decimal::ZERO && indicator[index - 1] < Decimal::ZERO {
+            signals[index + gap] = Some(Signal::Long);
+        } else if indicator[index] < Decimal::ZERO && indicator[index - 1] > Decimal::ZERO {
+            signals[index + gap] = Some(Signal::Short);
+        }
+    }
+    signals
+}
+
+/// First bar whose signal changes when the bars after it are hidden, i.e. the strategy read the future
+/// Only meaningful for strategies whose signals depend on the bars alone, like every built-in one
+/// Prefixes too short for the strategy (e.g. indicator warm up) count as no signal, any other error
+/// is returned
+pub fn find_look_ahead<S: Strategy + ?Sized>(
+    strategy: &mut S,
+    series: &PriceSeries,
+) -> Result<Option<usize>, MidasError> {
+    let signals = strategy.signals(series)?;
+    let candles = series.candles();
+    for (index, signal) in signals.iter().enumerate() {
+        let seen = match last_signal(strategy, &candles[..=index]) {
+            Ok(seen) => seen,
+            Err(MidasError::InsufficientData { .. }) => None,
+            Err(error) => return Err(error),
+        };
+        if seen != *signal {
+            return Ok(Some(index));
+        }
+    }
+    Ok(None)
+}
+
+fn last_signal<S: Strategy + ?Sized>(
+    strategy: &mut S,
+    bars: &[Candle],
+) -> Result<Option<Signal>, MidasError> {
+    let signals = strategy.signals(&PriceSeries::new(bars.to_vec()))?;
+    Ok(signals.last().copied().flatten())
+}
+
+/// Fast EMA / slow EMA cross from `get_cdc_action_zone`, 12 / 26 by default
+/// Closes only and no zones, `CdcZoneStrategy` follows the TradingView indicator
+#[derive(Debug, Clone, Copy)]
+pub struct CdcActionZoneStrategy {
+    pub fast: u32,
+    pub slow: u32,
+}
+
+impl Default for CdcActionZoneStrategy {
+    fn default() -> Self {
+        CdcActionZoneStrategy { fast: 12, slow: 26 }
+    }
+}
+
+impl Strategy for CdcActionZoneStrategy {
+    fn name(&self) -> String {
+        "cdc".to_string()
+    }
+    fn next(&mut self, bars: &[Candle]) -> Result<Option<Signal>, MidasError> {
+        last_signal(self, bars)
+    }
+    fn signals(&mut self, series: &PriceSeries) -> Result<Vec<Option<Signal>>, MidasError> {
+        let prices = series.closes();
+        let indicator = get_cdc_action_zone_with_periods(&prices, self.fast, self.slow)?;
+        Ok(cross_signals(
+            &indicator,
+            prices.len() - indicator.len(),
+            prices.len(),
+        ))
+    }
+}
+
+/// Buy and sell bars of `get_cdc_action_zone_with_zones`: long on the first green bar, short on the
+/// first red bar
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CdcZoneStrategy {
+    pub params: ICdcActionZoneParams,
+}
+
+impl Strategy for CdcZoneStrategy {
+    fn name(&self) -> String {
+        "cdc_zone".to_string()
+    }
+    fn next(&mut self, bars: &[Candle]) -> Result<Option<Signal>, MidasError> {
+        last_signal(self, bars)
+    }
+    fn signals(&mut self, series: &PriceSeries) -> Result<Vec<Option<Signal>>, MidasError> {
+        let cdc = get_cdc_action_zone_with_zones(
+            &series.opens(),
+            &series.highs(),
+            &series.lows(),
+            &series.closes(),
+            &self.params,
+        )?;
+        let mut signals: Vec<Option<Signal>> = vec![None; series.len() - cdc.actions.len()];
+        signals.extend(cdc.actions.iter().map(|action| match action {
+            Some(CdcAction::Buy) => Some(Signal::Long),
+            Some(CdcAction::Sell) => Some(Signal::Short),
+            None => None,
+        }));
+        Ok(signals)
+    }
+}
+
+/// Fast SMA / slow SMA cross from `get_ma_cross`
+#[derive(Debug, Clone, Copy)]
+pub struct MaCrossStrategy {
+    pub fast: u32,
+    pub slow: u32,
+}
+
+impl Default for MaCrossStrategy {
+    fn default() -> Self {
+        MaCrossStrategy { fast: 14, slow: 26 }
+    }
+}
+
+impl Strategy for MaCrossStrategy {
+    fn name(&self) -> String {
+        "ma_cross".to_string()
+    }
+    fn next(&mut self, bars: &[Candle]) -> Result<Option<Signal>, MidasError> {
+        last_signal(self, bars)
+    }
+    fn signals(&mut self, series: &PriceSeries) -> Result<Vec<Option<Signal>>, MidasError> {
+        let prices = series.closes();
+        let indicator = get_ma_cross(&prices, self.fast, self.slow)?;
+        Ok(cross_signals(
+            &indicator,
+            prices.len() - indicator.len(),
+            prices.len(),
+        ))
+    }
+}
+
+/// Which MACD crossing gives the signals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MacdCross {
+    /// Histogram crossing zero, i.e. the MACD line crossing its signal line
+    Histogram,
+    /// MACD line crossing zero, the CDC signal with the chosen smoothing
+    ZeroLine,
+}
+
+/// MACD crosses from `get_macd`
+#[derive(Debug, Clone, Copy)]
+pub struct MacdStrategy {
+    pub params: IMACDParams,
+    pub cross: MacdCross,
+}
+
+impl Strategy for MacdStrategy {
+    fn name(&self) -> String {
+        match self.cross {
+            MacdCross::Histogram => "macd_histogram".to_string(),
+            MacdCross::ZeroLine => "macd_zero_line".to_string(),
+        }
+    }
+    fn next(&mut self, bars: &[Candle]) -> Result<Option<Signal>, MidasError> {
+        last_signal(self, bars)
+    }
+    fn signals(&mut self, series: &PriceSeries) -> Result<Vec<Option<Signal>>, MidasError> {
+        let prices = series.closes();
+        let indicator = match self.cross {
+            MacdCross::Histogram => get_macd(&prices, &self.params)?.histogram,
+            MacdCross::ZeroLine => get_macd_line(
+                &prices,
+                self.params.fast,
+                self.params.slow,
+                self.params.oscillator_ma,
+            )?,
+        };
+        Ok(cross_signals(
+            &indicator,
+            prices.len() - indicator.len(),
+            prices.len(),
+        ))
+    }
+}
+
+/// Volatility bands of `BandStrategy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Band {
+    Bollinger {
+        period: u32,
+        deviations: Decimal,
+    },
+    Keltner {
+        ema_period: u32,
+        atr_period: u32,
+        multiplier: Decimal,
+    },
+    Donchian {
+        period: u32,
+    },
+}
+
+impl Band {
+    pub fn bands(&self, series: &PriceSeries) -> Result<IBands<Decimal>, MidasError> {
+        let closes = series.closes();
+        match *self {
+            Band::Bollinger { period, deviations } => {
+                get_bollinger_bands(&closes, period, deviations)
+            }
+            Band::Keltner {
+                ema_period,
+                atr_period,
+                multiplier,
+            } => get_keltner_channels(
+                &series.highs(),
+                &series.lows(),
+                &closes,
+                ema_period,
+                atr_period,
+                multiplier,
+            ),
+            Band::Donchian { period } => {
+                get_donchian_channels(&series.highs(), &series.lows(), &closes, period)
+            }
+        }
+    }
+}
+
+/// Breakout or re-entry signals of `get_band_signals`
+#[derive(Debug, Clone, Copy)]
+pub struct BandStrategy {
+    pub band: Band,
+    pub signal: BandSignal,
+}
+
+impl Strategy for BandStrategy {
+    fn name(&self) -> String {
+        let band = match self.band {
+            Band::Bollinger { .. } => "bollinger",
+            Band::Keltner { .. } => "keltner",
+            Band::Donchian { .. } => "donchian",
+        };
+        let signal = match self.signal {
+            BandSignal::Breakout => "breakout",
+            BandSignal::ReEntry => "re_entry",
+        };
+        format!("{band}_{signal}")
+    }
+    fn next(&mut self, bars: &[Candle]) -> Result<Option<Signal>, MidasError> {
+        last_signal(self, bars)
+    }
+    fn signals(&mut self, series: &PriceSeries) -> Result<Vec<Option<Signal>>, MidasError> {
+        let closes = series.closes();
+        let indicator = get_band_signals(&closes, &self.band.bands(series)?, self.signal)?;
+        Ok(cross_signals(
+            &indicator,
+            closes.len() - indicator.len(),
+            closes.len(),
+        ))
+    }
+}
+
+/// Strategies that used to be hardcoded in the backtest: CDC Action Zone and SMA 14 / 26 cross
+pub fn default_strategies() -> Vec<Box<dyn Strategy>> {
+    vec![
+        Box::new(CdcActionZoneStrategy::default()),
+        Box::new(MaCrossStrategy::default()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::mock::{mock_candles, MockHindsight};
+    use rust_decimal_macros::dec;
+
+    /// Long after two rising closes, close after two falling closes
+    struct Momentum;
+
+    impl Strategy for Momentum {
+        fn name(&self) -> String {
+            "momentum".to_string()
+        }
+        fn next(&mut self, bars: &[Candle]) -> Result<Option<Signal>, MidasError> {
+            if bars.len() < 3 {
+                return Ok(None);
+            }
+            let [first, second, third] = [
+                bars[bars.len() - 3].close,
+                bars[bars.len() - 2].close,
+                bars[bars.len() - 1].close,
+            ];
+            Ok(if first < second && second < third {
+                Some(Signal::Long)
+            } else if first > second && second > third {
+                Some(Signal::Close)
+            } else {
+                None
+            })
+        }
+    }
+
+    /// Fails on fewer than 10 bars with an error other than `InsufficientData`
+    struct Picky;
+
+    impl Strategy for Picky {
+        fn name(&self) -> String {
+            "picky".to_string()
+        }
+        fn next(&mut self, bars: &[Candle]) -> Result<Option<Signal>, MidasError> {
+            last_signal(self, bars)
+        }
+        fn signals(&mut self, series: &PriceSeries) -> Result<Vec<Option<Signal>>, MidasError> {
+            if series.len() < 10 {
+                return Err(MidasError::InvalidInput("Too few bars".to_string()));
+            }
+            Ok(vec![None; series.len()])
+        }
+    }
+
+    #[test]
+    fn test_find_look_ahead() {
+        let series = PriceSeries::new(mock_candles());
+
+        assert!(find_look_ahead(&mut Momentum, &series).unwrap().is_none());
+        assert!(
+            find_look_ahead(&mut MaCrossStrategy { fast: 3, slow: 5 }, &series)
+                .unwrap()
+                .is_none()
+        );
+        // * Highest close is the last one, the long on bar 28 needs bar 29
+        assert!(find_look_ahead(&mut MockHindsight, &series).unwrap() == Some(28));
+        // * Only a warm up error on a prefix means no signal, others are returned
+        assert!(matches!(
+            find_look_ahead(&mut Picky, &series),
+            Err(MidasError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_cross_signals() {
+        let indicator = [dec!(-1), dec!(1), dec!(2), dec!(-1)];
+
+        let signals = cross_signals(&indicator, 2, 6);
+
+        assert!(
+            signals
+                == vec![
+                    None,
+                    None,
+                    None,
+                    Some(Signal::Long),
+                    None,
+                    Some(Signal::Short)
+                ]
+        );
+    }
+
+    #[test]
+    fn test_macd_strategy() {
+        let series = PriceSeries::new(mock_candles());
+        let params = IMACDParams {
+            fast: 3,
+            slow: 6,
+            signal: 4,
+            ..IMACDParams::default()
+        };
+        let mut zero_line = MacdStrategy {
+            params,
+            cross: MacdCross::ZeroLine,
+        };
+        let mut histogram = MacdStrategy {
+            params,
+            cross: MacdCross::Histogram,
+        };
+
+        // * With EMAs the zero line cross is the CDC signal
+        let cdc = CdcActionZoneStrategy { fast: 3, slow: 6 }
+            .signals(&series)
+            .unwrap();
+        assert!(zero_line.signals(&series).unwrap() == cdc);
+        let signals = histogram.signals(&series).unwrap();
+        assert!(signals.len() == series.len());
+        assert!(signals.iter().any(|signal| signal.is_some()));
+        assert!(signals != cdc);
+    }
+
+    #[test]
+    fn test_cdc_zone_strategy() {
+        let series = PriceSeries::new(mock_candles());
+        let mut strategy = CdcZoneStrategy {
+            params: ICdcActionZoneParams {
+                smoothing: 2,
+                fast: 3,
+                slow: 6,
+            },
+        };
+
+        let signals = strategy.signals(&series).unwrap();
+        assert!(signals.len() == series.len());
+        assert!(signals[..6].iter().all(|signal| signal.is_none()));
+        assert!(signals.iter().flatten().next() == Some(&Signal::Long));
+        assert!(find_look_ahead(&mut strategy, &series).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_band_strategy() {
+        let series = PriceSeries::new(mock_candles());
+        let mut breakout = BandStrategy {
+            band: Band::Bollinger {
+                period: 5,
+                deviations: dec!(1),
+            },
+            signal: BandSignal::Breakout,
+        };
+        let mut donchian = BandStrategy {
+            band: Band::Donchian { period: 3 },
+            signal: BandSignal::Breakout,
+        };
+
+        let signals = breakout.signals(&series).unwrap();
+        assert!(breakout.name() == "bollinger_breakout");
+        assert!(signals.len() == series.len());
+        assert!(signals.iter().any(|signal| signal.is_some()));
+        // * Already long from the first channel, 19.57 breaks below the 3 previous lows and
+        // * 18.5 above the 3 previous highs
+        let signals = donchian.signals(&series).unwrap();
+        assert!(signals[14] == Some(Signal::Short));
+        assert!(signals[19] == Some(Signal::Long));
+        assert!(signals.iter().flatten().count() == 2);
+        assert!(find_look_ahead(&mut donchian, &series).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_strategy_signals_from_next() {
+        let series = PriceSeries::new(mock_candles());
+
+        let signals = Momentum.signals(&series).unwrap();
+
+        assert!(signals.len() == series.len());
+        assert!(signals[..2] == [None, None]);
+        assert!(signals[2] == Some(Signal::Long));
+        // * 20.56 -> 20.13 -> 19.57
+        assert!(signals[14] == Some(Signal::Close));
+    }
+}
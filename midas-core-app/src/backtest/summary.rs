@@ -7,13 +7,23 @@ use num::{FromPrimitive, ToPrimitive};
 use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
 
-use crate::utils::{mean, calculate_maximum_drawdown};
+use crate::error::MidasError;
+use crate::price_manipulation::drawdown::get_longest_drawdown_duration;
+use crate::utils::{calculate_maximum_drawdown, mean};
 
-use super::IBackTestingResult;
+use super::benchmark::{get_benchmark_metrics, IBenchmarkMetrics};
+use super::equity_curve::get_curve_maximum_drawdown;
+use super::metrics::{
+    get_annualized_volatility, get_bar_returns, get_cagr, get_exposure, get_recovery_factor,
+    get_sharpe_ratio, get_sortino_ratio, get_sqn, get_ulcer_index, IMetricsParams,
+};
+use super::{IBackTestingResult, IOpenPosition, POSITION};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IBackTestingSummary {
     pub total_trade: u16,
+    pub long_trade: u16,
+    pub short_trade: u16,
     pub winning_trade: u16,
     pub losing_trade: u16,
     pub percent_profitable: Decimal,
@@ -25,14 +35,50 @@ pub struct IBackTestingSummary {
     pub max_consec_win: u16,
     pub max_consec_lose: u16,
     pub profit_factor: Decimal,
+    /// Deepest fall of equity from its peak, bar by bar when the result has an equity curve
     pub maximum_drawdown: Decimal,
+    /// Bars from a peak until equity gets back to it, trades when the result has no equity curve
+    pub longest_drawdown_duration: usize,
+    /// Return of the portfolio net of spread, slippage and fees
+    /// Compounded from trade returns when the result has no equity curve
     pub returns: Decimal,
+    /// Compounded trade return before any cost, as if every trade used the whole equity
+    pub gross_returns: Decimal,
+    /// Fees paid on every fill, in quote currency
+    pub total_fees: Decimal,
+    /// Ratios below are annualized with `IMetricsParams`, bar based ones are zero without an equity curve
+    pub sharpe_ratio: Decimal,
+    pub sortino_ratio: Decimal,
+    /// CAGR over the absolute maximum drawdown
+    pub calmar_ratio: Decimal,
+    pub cagr: Decimal,
+    pub annualized_volatility: Decimal,
+    /// Share of bars with an open position
+    pub exposure: Decimal,
+    /// Mean net return per trade
+    pub expectancy: Decimal,
+    /// System quality number of the trade returns
+    pub sqn: Decimal,
+    pub ulcer_index: Decimal,
+    /// Net profit over the largest fall of equity, in quote currency
+    pub recovery_factor: Decimal,
+    /// Relative to `IBackTestingResult::benchmark`, zero without a benchmark
+    pub benchmark_return: Decimal,
+    pub excess_return: Decimal,
+    pub alpha: Decimal,
+    pub beta: Decimal,
+    pub tracking_error: Decimal,
+    pub information_ratio: Decimal,
+    /// Position left open at the end of the data, not counted in the trade statistics above
+    pub open_position: Option<IOpenPosition<Decimal>>,
 }
 
 impl IBackTestingSummary {
     pub fn new() -> IBackTestingSummary {
         IBackTestingSummary {
             total_trade: 0,
+            long_trade: 0,
+            short_trade: 0,
             winning_trade: 0,
             losing_trade: 0,
             percent_profitable: dec!(0),
@@ -45,16 +91,55 @@ impl IBackTestingSummary {
             max_consec_lose: 0,
             profit_factor: dec!(0),
             maximum_drawdown: dec!(0),
+            longest_drawdown_duration: 0,
             returns: dec!(0),
+            gross_returns: dec!(0),
+            total_fees: dec!(0),
+            sharpe_ratio: dec!(0),
+            sortino_ratio: dec!(0),
+            calmar_ratio: dec!(0),
+            cagr: dec!(0),
+            annualized_volatility: dec!(0),
+            exposure: dec!(0),
+            expectancy: dec!(0),
+            sqn: dec!(0),
+            ulcer_index: dec!(0),
+            recovery_factor: dec!(0),
+            benchmark_return: dec!(0),
+            excess_return: dec!(0),
+            alpha: dec!(0),
+            beta: dec!(0),
+            tracking_error: dec!(0),
+            information_ratio: dec!(0),
+            open_position: None,
         }
     }
-    pub fn calculate(result: &IBackTestingResult<Decimal>) -> IBackTestingSummary {
-        let returns: Vec<Decimal> = result.get_return();
+    /// Summary with a zero risk-free rate and 365 periods per year
+    pub fn calculate(
+        result: &IBackTestingResult<Decimal>,
+    ) -> Result<IBackTestingSummary, MidasError> {
+        IBackTestingSummary::calculate_with_metrics(result, &IMetricsParams::new())
+    }
+    /// Ratios with a zero denominator (e.g. profit factor without any losing trade) are reported as zero
+    pub fn calculate_with_metrics(
+        result: &IBackTestingResult<Decimal>,
+        metrics: &IMetricsParams,
+    ) -> Result<IBackTestingSummary, MidasError> {
+        let returns: Vec<Decimal> = result.get_return()?;
 
         let mut summary: IBackTestingSummary = IBackTestingSummary::new();
 
         // * Total Trade
-        summary.total_trade = IBackTestingSummary::get_total_trade(&returns);
+        summary.total_trade = IBackTestingSummary::get_total_trade(&returns)?;
+
+        // * Long Trade & Short Trade
+        result
+            .get_entry_and_close()?
+            .iter()
+            .for_each(|trade| match trade.position {
+                POSITION::Short => summary.short_trade += 1,
+                _ => summary.long_trade += 1,
+            });
 
         // * Winning Trade & Losing Trade & Biggest Win & Biggest Loss
         returns
@@ -78,42 +163,114 @@ impl IBackTestingSummary {
         summary.percent_profitable = IBackTestingSummary::get_percent_profitable(&summary);
 
         // * Average Win & Average Loss & Avg.Win / Avg.Loss Ratio
-        let positive_return: Vec<Decimal> = IBackTestingResult::filter_positive_return(result);
-        let negative_return: Vec<Decimal> = IBackTestingResult::filter_negative_return(result);
-        summary.average_win = mean(
-            positive_return.as_slice(),
-            &Decimal::from_usize(positive_return.len()).expect("Failed to parse usize to dec"),
-        );
-        summary.average_losing = mean(
-            negative_return.as_slice(),
-            &Decimal::from_usize(negative_return.len()).expect("Failed to parse usize to dec"),
-        );
-        summary.avg_win_loss_ratio =
-            summary.average_win / (Decimal::NEGATIVE_ONE * summary.average_losing);
+        let positive_return: Vec<Decimal> = IBackTestingResult::filter_positive_return(result)?;
+        let negative_return: Vec<Decimal> = IBackTestingResult::filter_negative_return(result)?;
+        summary.average_win = IBackTestingSummary::get_average(&positive_return)?;
+        summary.average_losing = IBackTestingSummary::get_average(&negative_return)?;
+        summary.avg_win_loss_ratio = summary
+            .average_win
+            .checked_div(Decimal::NEGATIVE_ONE * summary.average_losing)
+            .unwrap_or(Decimal::ZERO);
 
         // * Consecutive win and loss
         summary.max_consec_win = IBackTestingSummary::get_max_consective_win(&returns);
         summary.max_consec_lose = IBackTestingSummary::get_max_consecutive_loss(&returns);
 
         // * Profit factor and Returns
-        let [portfolio_value, gross_profit, gross_loss] =
-            IBackTestingSummary::get_pv_gp_gl(&returns);
-        summary.profit_factor = gross_profit / gross_loss;
+        let [gross_profit, gross_loss] = IBackTestingSummary::get_gp_gl(result)?;
+        summary.profit_factor = gross_profit
+            .checked_div(gross_loss)
+            .unwrap_or(Decimal::ZERO);
+        let portfolio_value = match result.equity_curve.last() {
+            Some(point) => point
+                .equity
+                .checked_div(result.initial_capital)
+                .ok_or_else(|| MidasError::InvalidInput("Zero initial capital".to_string()))?,
+            None => IBackTestingSummary::get_pv_gp_gl(&returns)?[0],
+        };
         summary.returns = portfolio_value - Decimal::ONE;
 
-        summary.maximum_drawdown = IBackTestingSummary::get_maximum_drawdown(&returns);
+        // * Gross Returns & Fees
+        let [gross_portfolio_value, _, _] =
+            IBackTestingSummary::get_pv_gp_gl(&result.get_gross_return()?)?;
+        summary.gross_returns = gross_portfolio_value - Decimal::ONE;
+        summary.total_fees = result.result.iter().map(|fill| fill.fee).sum();
+
+        // * Maximum drawdown, including the drawdown inside open trades
+        summary.maximum_drawdown = if result.equity_curve.is_empty() {
+            IBackTestingSummary::get_maximum_drawdown(&returns)?
+        } else {
+            get_curve_maximum_drawdown(&result.equity_curve)
+        };
+        // * Longest drawdown, in bars or in trades without an equity curve
+        let equity_value: Vec<Decimal> = if result.equity_curve.is_empty() {
+            IBackTestingSummary::get_compounded_equity(&returns)?
+        } else {
+            std::iter::once(result.initial_capital)
+                .chain(result.equity_curve.iter().map(|point| point.equity))
+                .collect()
+        };
+        summary.longest_drawdown_duration = get_longest_drawdown_duration(&equity_value)?;
+
+        // * Risk-adjusted metrics
+        let curve = &result.equity_curve;
+        let bar_returns = get_bar_returns(curve, result.initial_capital)?;
+        summary.sharpe_ratio = get_sharpe_ratio(&bar_returns, metrics)?;
+        summary.sortino_ratio = get_sortino_ratio(&bar_returns, metrics)?;
+        summary.annualized_volatility = get_annualized_volatility(&bar_returns, metrics)?;
+        summary.cagr = get_cagr(summary.returns, curve.len(), metrics)?;
+        summary.calmar_ratio = summary
+            .cagr
+            .checked_div(summary.maximum_drawdown.abs())
+            .unwrap_or(Decimal::ZERO);
+        summary.exposure = get_exposure(curve);
+        summary.expectancy = IBackTestingSummary::get_average(&returns)?;
+        summary.sqn = get_sqn(&returns)?;
+        summary.ulcer_index = get_ulcer_index(curve)?;
+        summary.recovery_factor = get_recovery_factor(curve, result.initial_capital);
+
+        // * Benchmark
+        if !result.benchmark.is_empty() {
+            let IBenchmarkMetrics {
+                benchmark_return,
+                excess_return,
+                alpha,
+                beta,
+                tracking_error,
+                information_ratio,
+            } = get_benchmark_metrics(&bar_returns, &result.benchmark, metrics)?;
+            summary.benchmark_return = benchmark_return;
+            summary.excess_return = excess_return;
+            summary.alpha = alpha;
+            summary.beta = beta;
+            summary.tracking_error = tracking_error;
+            summary.information_ratio = information_ratio;
+        }
 
-        summary
+        // * Open Position
+        summary.open_position = result.open_position;
+
+        Ok(summary)
     }
-    pub fn get_total_trade(returns: &[Decimal]) -> u16 {
-        returns
-            .len()
-            .to_u16()
-            .expect("Failed to parse from usize to u16")
+    pub fn get_total_trade(returns: &[Decimal]) -> Result<u16, MidasError> {
+        returns.len().to_u16().ok_or_else(|| {
+            MidasError::ArithmeticOverflow(format!("{} trades do not fit in u16", returns.len()))
+        })
     }
     pub fn get_percent_profitable(&self) -> Decimal {
-        Decimal::from_u16(self.winning_trade).expect("Failed to parse Decimal from u16")
-            / Decimal::from_u16(self.total_trade).expect("Failed to parse Decimal from u16")
+        Decimal::from(self.winning_trade)
+            .checked_div(Decimal::from(self.total_trade))
+            .unwrap_or(Decimal::ZERO)
+    }
+    /// Mean of the returns, zero when there is no return
+    pub fn get_average(returns: &[Decimal]) -> Result<Decimal, MidasError> {
+        if returns.is_empty() {
+            return Ok(Decimal::ZERO);
+        }
+        let length = Decimal::from_usize(returns.len()).ok_or_else(|| {
+            MidasError::ArithmeticOverflow(format!("{} returns as Decimal", returns.len()))
+        })?;
+        Ok(mean(returns, &length))
     }
     pub fn get_max_consective_win(returns: &[Decimal]) -> u16 {
         let mut consecutive_win: u16 = 0;
@@ -126,11 +283,12 @@ impl IBackTestingSummary {
                 consecutive_win = 0;
             }
         }
-        *record
+        record
             .iter()
+            .copied()
             .max()
-            .expect("Failed to parse max consecutive win")
-            .max(&consecutive_win)
+            .unwrap_or(0)
+            .max(consecutive_win)
     }
     pub fn get_max_consecutive_loss(returns: &[Decimal]) -> u16 {
         let mut consecutive_loss: u16 = 0;
@@ -144,55 +302,167 @@ impl IBackTestingSummary {
                 consecutive_loss = 0;
             }
         }
-        *record
+        record
             .iter()
+            .copied()
             .max()
-            .expect("Failed to parse max consecutive loss")
-            .max(&consecutive_loss)
+            .unwrap_or(0)
+            .max(consecutive_loss)
     }
-    pub fn get_pv_gp_gl(returns: &[Decimal]) -> [Decimal; 3] {
+    pub fn get_pv_gp_gl(returns: &[Decimal]) -> Result<[Decimal; 3], MidasError> {
         // 1 = Initial fund
         let mut portfolio_value: Decimal = Decimal::ONE;
         let mut gross_profit: Decimal = Decimal::ZERO;
         let mut gross_loss: Decimal = Decimal::ZERO;
-        returns
-            .iter()
-            .map(|value: &Decimal| {
-                let compounded: Decimal = portfolio_value * (Decimal::ONE + value);
+        for value in returns {
+            let compounded: Decimal = compound(portfolio_value, *value)?;
 
-                match compounded.cmp(&portfolio_value) {
-                    Ordering::Greater => gross_profit += compounded - portfolio_value,
-                    Ordering::Equal => gross_profit += compounded - portfolio_value,
-                    Ordering::Less => gross_loss += portfolio_value - compounded,
-                }
-                portfolio_value = compounded;
-            })
-            .for_each(drop);
+            match compounded.cmp(&portfolio_value) {
+                Ordering::Greater => gross_profit += compounded - portfolio_value,
+                Ordering::Equal => gross_profit += compounded - portfolio_value,
+                Ordering::Less => gross_loss += portfolio_value - compounded,
+            }
+            portfolio_value = compounded;
+        }
 
-        [portfolio_value, gross_profit, gross_loss]
+        Ok([portfolio_value, gross_profit, gross_loss])
+    }
+    /// Gross profit and gross loss in quote currency, from the profit of each trade
+    pub fn get_gp_gl(result: &IBackTestingResult<Decimal>) -> Result<[Decimal; 2], MidasError> {
+        Ok(result.get_entry_and_close()?.iter().fold(
+            [Decimal::ZERO, Decimal::ZERO],
+            |[profit, loss], trade| {
+                if trade.pnl >= Decimal::ZERO {
+                    [profit + trade.pnl, loss]
+                } else {
+                    [profit, loss - trade.pnl]
+                }
+            },
+        ))
     }
     /// Calculate [maximum drawdown](https://www.investopedia.com/terms/m/maximum-drawdown-mdd.asp) from returns
-    /// Find n-1 peak and n peak then calculate drawdown between n-1 and n
+    /// Deepest fall of the compounded equity from its running peak
     /// @returns Return the smallest value of drawdown
-    pub fn get_maximum_drawdown(returns: &[Decimal]) -> Decimal {
+    pub fn get_maximum_drawdown(returns: &[Decimal]) -> Result<Decimal, MidasError> {
+        let equity_value = IBackTestingSummary::get_compounded_equity(returns)?;
+
+        Ok(calculate_maximum_drawdown(equity_value)?.unwrap_or(Decimal::ZERO))
+    }
+    /// Equity after every trade, starting from 1
+    pub fn get_compounded_equity(returns: &[Decimal]) -> Result<Vec<Decimal>, MidasError> {
         // Initial Value
         let mut portfolio_value: Decimal = Decimal::ONE;
         let mut equity_value: Vec<Decimal> = vec![portfolio_value];
-        returns
-            .iter()
-            .map(|value: &Decimal| {
-                // Calculate new equity value
-                portfolio_value = portfolio_value * (Decimal::ONE + value);
-                equity_value.push(portfolio_value);
-            })
-            .for_each(drop);
+        for value in returns {
+            // Calculate new equity value
+            portfolio_value = compound(portfolio_value, *value)?;
+            equity_value.push(portfolio_value);
+        }
 
-        calculate_maximum_drawdown(equity_value)
+        Ok(equity_value)
     }
 }
 
+fn compound(portfolio_value: Decimal, value: Decimal) -> Result<Decimal, MidasError> {
+    portfolio_value
+        .checked_mul(Decimal::ONE + value)
+        .ok_or_else(|| MidasError::ArithmeticOverflow("compounding portfolio value".to_string()))
+}
+
 impl Default for IBackTestingSummary {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::IBackTestingSingleResult;
+    use crate::utils::mock::mock_fill;
+
+    fn mock_result(trades: &[(Decimal, Decimal)]) -> IBackTestingResult<Decimal> {
+        let mut result: Vec<IBackTestingSingleResult<Decimal>> = Vec::new();
+        for (index, (entry, closing)) in trades.iter().enumerate() {
+            result.push(mock_fill(POSITION::Long, *entry, index * 2));
+            result.push(mock_fill(POSITION::Close, *closing, index * 2 + 1));
+        }
+        IBackTestingResult {
+            result,
+            equity_curve: Vec::new(),
+            initial_capital: dec!(1),
+            benchmark: Vec::new(),
+            open_position: None,
+        }
+    }
+
+    #[test]
+    fn test_calculate_summary() {
+        let result = mock_result(&[
+            (dec!(10), dec!(12)),
+            (dec!(12), dec!(9)),
+            (dec!(9), dec!(18)),
+        ]);
+
+        let summary = IBackTestingSummary::calculate(&result).unwrap();
+
+        assert!(summary.total_trade == 3);
+        assert!(summary.winning_trade == 2);
+        assert!(summary.losing_trade == 1);
+        assert!(summary.largest_win == dec!(1));
+        assert!(summary.largest_losing == dec!(-0.25));
+        // * 1.2 * 0.75 * 2
+        assert!(summary.returns == dec!(0.8));
+        assert!(summary.gross_returns == summary.returns);
+        assert!(summary.total_fees == Decimal::ZERO);
+        // * (0.2 - 0.25 + 1) / 3, bar based metrics need an equity curve
+        assert!(summary.expectancy == dec!(0.95) / dec!(3));
+        // * 1.2 -> 0.9, back above the peak after the next trade
+        assert!(summary.maximum_drawdown == dec!(-0.25));
+        assert!(summary.longest_drawdown_duration == 2);
+        assert!(summary.sqn > Decimal::ZERO);
+        assert!(summary.sharpe_ratio == Decimal::ZERO);
+        assert!(summary.exposure == Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_calculate_summary_net_of_fees() {
+        let mut result = mock_result(&[(dec!(10), dec!(12))]);
+        result.result[0].fee = dec!(0.1);
+        result.result[1].fee = dec!(0.1);
+
+        let summary = IBackTestingSummary::calculate(&result).unwrap();
+
+        assert!(summary.gross_returns == dec!(0.2));
+        // * (12 - 10 - 0.2) / 10
+        assert!(summary.returns == dec!(0.18));
+        assert!(summary.total_fees == dec!(0.2));
+    }
+
+    #[test]
+    fn test_calculate_summary_without_losing_trade() {
+        let summary = IBackTestingSummary::calculate(&mock_result(&[])).unwrap();
+        assert!(summary.total_trade == 0);
+        assert!(summary.percent_profitable == Decimal::ZERO);
+
+        let summary =
+            IBackTestingSummary::calculate(&mock_result(&[(dec!(10), dec!(11))])).unwrap();
+        assert!(summary.profit_factor == Decimal::ZERO);
+        assert!(summary.average_win == dec!(0.1));
+    }
+
+    #[test]
+    fn test_calculate_summary_with_short() {
+        let mut result = mock_result(&[(dec!(10), dec!(12))]);
+        result.result.push(mock_fill(POSITION::Short, dec!(12), 2));
+        result.result.push(mock_fill(POSITION::Cover, dec!(9), 3));
+
+        let summary = IBackTestingSummary::calculate(&result).unwrap();
+
+        assert!(summary.long_trade == 1);
+        assert!(summary.short_trade == 1);
+        assert!(summary.winning_trade == 2);
+        // * 1.2 * 1.25
+        assert!(summary.returns == dec!(0.5));
+    }
+}
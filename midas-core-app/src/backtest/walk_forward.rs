@@ -0,0 +1,282 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::engine::get_positions_from_signals;
+use super::equity_curve::IEquityPoint;
+use super::optimizer::{grid_search, IGridSearchParams};
+use super::strategy::{Signal, Strategy};
+use super::summary::IBackTestingSummary;
+use super::IBackTestingParams;
+use crate::error::MidasError;
+use crate::get_price::candle::PriceSeries;
+
+/// Bar range `[start, end)` of the series
+pub type BarRange = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowMode {
+    /// In-sample windows keep their length and move forward
+    Rolling,
+    /// In-sample windows all start at the first bar and grow
+    Anchored,
+}
+
+#[derive(Debug, Clone)]
+pub struct IWalkForwardParams {
+    /// Bars used to optimize, the first in-sample window for `Anchored`
+    pub in_sample: usize,
+    /// Bars evaluated after each in-sample window, also the step between folds
+    pub out_of_sample: usize,
+    pub mode: WindowMode,
+    pub search: IGridSearchParams,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IWalkForwardFold {
+    pub in_sample: BarRange,
+    pub out_of_sample: BarRange,
+    /// Best parameters of the in-sample window
+    pub parameters: Vec<Decimal>,
+    pub in_sample_score: Decimal,
+    pub in_sample_return: Decimal,
+    /// Out-of-sample run of `parameters`, positions still open at the end are marked to market
+    pub out_of_sample_summary: IBackTestingSummary,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IWalkForwardResult {
+    pub folds: Vec<IWalkForwardFold>,
+    /// Out-of-sample equity of every fold chained together, indices refer to the whole series
+    pub equity_curve: Vec<IEquityPoint<Decimal>>,
+    pub out_of_sample_return: Decimal,
+    /// Return per out-of-sample bar over return per in-sample bar, both the sum of the fold returns
+    /// over the sum of the fold bars, close to 1 when parameters keep their performance on unseen data
+    pub efficiency_ratio: Decimal,
+}
+
+/// Bar ranges of every in-sample and out-of-sample window
+pub fn get_walk_forward_windows(
+    length: usize,
+    params: &IWalkForwardParams,
+) -> Result<Vec<(BarRange, BarRange)>, MidasError> {
+    if params.in_sample == 0 || params.out_of_sample == 0 {
+        return Err(MidasError::InvalidInput(
+            "Walk-forward windows need at least one bar".to_string(),
+        ));
+    }
+    if length < params.in_sample + params.out_of_sample {
+        return Err(MidasError::InsufficientData {
+            required: params.in_sample + params.out_of_sample,
+            available: length,
+        });
+    }
+    let mut windows = Vec::new();
+    let mut in_sample_end = params.in_sample;
+    while in_sample_end + params.out_of_sample <= length {
+        let start = match params.mode {
+            WindowMode::Rolling => in_sample_end - params.in_sample,
+            WindowMode::Anchored => 0,
+        };
+        windows.push((
+            (start, in_sample_end),
+            (in_sample_end, in_sample_end + params.out_of_sample),
+        ));
+        in_sample_end += params.out_of_sample;
+    }
+    Ok(windows)
+}
+
+fn get_slice(series: &PriceSeries, (start, end): BarRange) -> PriceSeries {
+    PriceSeries::new(series.candles()[start..end].to_vec())
+}
+
+/// Optimize on every in-sample window with `grid_search`, then trade the best parameters on the
+/// following out-of-sample window
+/// The out-of-sample strategy sees the in-sample bars as indicator warm up but only trades unseen bars
+pub fn walk_forward<F>(
+    series: &PriceSeries,
+    params: &IBackTestingParams,
+    walk_forward: &IWalkForwardParams,
+    build: F,
+) -> Result<IWalkForwardResult, MidasError>
+where
+    F: Fn(&[Decimal], &mut IBackTestingParams) -> Result<Box<dyn Strategy>, MidasError> + Sync,
+{
+    if params.initial_capital <= Decimal::ZERO {
+        return Err(MidasError::InvalidInput(format!(
+            "Initial capital {} must be positive",
+            params.initial_capital
+        )));
+    }
+    let mut folds: Vec<IWalkForwardFold> = Vec::new();
+    let mut equity_curve: Vec<IEquityPoint<Decimal>> = Vec::new();
+    let mut equity = params.initial_capital;
+    let mut peak = params.initial_capital;
+    let mut in_sample_bars = Decimal::ZERO;
+    let mut in_sample_growth = Decimal::ZERO;
+    let mut out_of_sample_growth = Decimal::ZERO;
+
+    for (fold, (in_sample, out_of_sample)) in get_walk_forward_windows(series.len(), walk_forward)?
+        .into_iter()
+        .enumerate()
+    {
+        let optimization = grid_search(
+            &get_slice(series, in_sample),
+            params,
+            &walk_forward.search,
+            &build,
+        )?;
+        let best = optimization.best().ok_or_else(|| {
+            MidasError::InvalidInput(format!("No parameters could be backtested in fold {fold}"))
+        })?;
+
+        // * Warm up on the in-sample bars, trade from the first out-of-sample bar
+        let window = get_slice(series, (in_sample.0, out_of_sample.1));
+        let warm_up = out_of_sample.0 - in_sample.0;
+        let mut fold_params = params.clone();
+        let mut strategy = build(&best.parameters, &mut fold_params)?;
+        let mut signals: Vec<Option<Signal>> = strategy.signals(&window)?;
+        if signals.len() != window.len() {
+            return Err(MidasError::InvalidInput(format!(
+                "{} gave {} signals for {} bars in fold {fold}",
+                strategy.name(),
+                signals.len(),
+                window.len()
+            )));
+        }
+        signals[..warm_up].fill(None);
+        let mut result = get_positions_from_signals(&signals, &window, &fold_params)?;
+        result.equity_curve.drain(..warm_up);
+        result.benchmark.drain(..warm_up);
+        if let Some(first) = result.benchmark.first_mut() {
+            *first = Decimal::ZERO;
+        }
+        let summary =
+            IBackTestingSummary::calculate_with_metrics(&result, &walk_forward.search.metrics)?;
+
+        // * Chain the fold on the equity left by the previous one
+        let scale = equity
+            .checked_div(fold_params.initial_capital)
+            .ok_or_else(|| {
+                MidasError::InvalidInput(format!(
+                    "Initial capital {} of fold {fold} must not be zero",
+                    fold_params.initial_capital
+                ))
+            })?;
+        for point in &result.equity_curve {
+            let scaled = point.equity * scale;
+            peak = peak.max(scaled);
+            equity_curve.push(IEquityPoint {
+                timestamp: point.timestamp,
+                index: point.index + in_sample.0,
+                cash: point.cash * scale,
+                quantity: point.quantity * scale,
+                position_value: point.position_value * scale,
+                equity: scaled,
+                drawdown: (scaled - peak) / peak,
+            });
+        }
+        equity = equity_curve.last().map_or(equity, |point| point.equity);
+
+        in_sample_bars += Decimal::from(in_sample.1 - in_sample.0);
+        in_sample_growth += best.summary.returns;
+        out_of_sample_growth += summary.returns;
+        folds.push(IWalkForwardFold {
+            in_sample,
+            out_of_sample,
+            parameters: best.parameters.clone(),
+            in_sample_score: best.score,
+            in_sample_return: best.summary.returns,
+            out_of_sample_summary: summary,
+        });
+    }
+
+    let out_of_sample_return = equity / params.initial_capital - Decimal::ONE;
+    let out_of_sample_bars = Decimal::from(equity_curve.len());
+    let efficiency_ratio = (out_of_sample_growth / out_of_sample_bars)
+        .checked_div(in_sample_growth / in_sample_bars)
+        .unwrap_or(Decimal::ZERO);
+    Ok(IWalkForwardResult {
+        folds,
+        equity_curve,
+        out_of_sample_return,
+        efficiency_ratio,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::metrics::IMetricsParams;
+    use crate::backtest::optimizer::{IParameterRange, Objective};
+    use crate::utils::mock::{mock_candles, mock_ma_cross};
+    use rust_decimal_macros::dec;
+
+    fn walk_forward_params(mode: WindowMode) -> IWalkForwardParams {
+        IWalkForwardParams {
+            in_sample: 10,
+            out_of_sample: 5,
+            mode,
+            search: IGridSearchParams {
+                ranges: vec![
+                    IParameterRange::new("fast", dec!(2), dec!(3), dec!(1)).unwrap(),
+                    IParameterRange::new("slow", dec!(4), dec!(5), dec!(1)).unwrap(),
+                ],
+                objective: Objective::Returns,
+                metrics: IMetricsParams::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_walk_forward_windows() {
+        let rolling = get_walk_forward_windows(30, &walk_forward_params(WindowMode::Rolling));
+        let anchored = get_walk_forward_windows(30, &walk_forward_params(WindowMode::Anchored));
+
+        assert!(
+            rolling.unwrap()
+                == vec![
+                    ((0, 10), (10, 15)),
+                    ((5, 15), (15, 20)),
+                    ((10, 20), (20, 25)),
+                    ((15, 25), (25, 30)),
+                ]
+        );
+        assert!(anchored.unwrap()[3] == ((0, 25), (25, 30)));
+        assert!(get_walk_forward_windows(12, &walk_forward_params(WindowMode::Rolling)).is_err());
+    }
+
+    #[test]
+    fn test_walk_forward() {
+        let series = PriceSeries::new(mock_candles());
+        let params = IBackTestingParams::new("mock", 30);
+
+        let result = walk_forward(
+            &series,
+            &params,
+            &walk_forward_params(WindowMode::Rolling),
+            mock_ma_cross,
+        )
+        .unwrap();
+
+        assert!(result.folds.len() == 4);
+        assert!(result.equity_curve.len() == 20);
+        assert!(result.equity_curve[0].index == 10);
+        assert!(result.equity_curve[19].index == 29);
+        // * Nothing is traded before the first out-of-sample bar closes
+        assert!(result.equity_curve[0].equity == params.initial_capital);
+        let last = result.equity_curve[19].equity;
+        assert!(result.out_of_sample_return == last / params.initial_capital - Decimal::ONE);
+        let broke = |parameters: &[Decimal], params: &mut IBackTestingParams| {
+            params.initial_capital = Decimal::ZERO;
+            mock_ma_cross(parameters, params)
+        };
+        assert!(walk_forward(
+            &series,
+            &params,
+            &walk_forward_params(WindowMode::Rolling),
+            broke
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MidasError {
+    /// Request failed or the server answered with an error status
+    Network(String),
+    /// Response or file content could not be parsed
+    Decode(String),
+    /// Local file could not be read
+    Io(String),
+    /// Not enough values for the requested calculation
+    InsufficientData { required: usize, available: usize },
+    /// Period is zero or inconsistent with other periods (e.g. fast > slow)
+    InvalidPeriod(String),
+    /// Numeric conversion or arithmetic went out of range
+    ArithmeticOverflow(String),
+    /// Any other argument the caller has to fix (unknown symbol, unsupported file, ...)
+    InvalidInput(String),
+}
+
+impl fmt::Display for MidasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MidasError::Network(message) => write!(f, "Network error: {message}"),
+            MidasError::Decode(message) => write!(f, "Decode error: {message}"),
+            MidasError::Io(message) => write!(f, "IO error: {message}"),
+            MidasError::InsufficientData {
+                required,
+                available,
+            } => write!(
+                f,
+                "Insufficient data: {required} values required, {available} available"
+            ),
+            MidasError::InvalidPeriod(message) => write!(f, "Invalid period: {message}"),
+            MidasError::ArithmeticOverflow(message) => {
+                write!(f, "Arithmetic overflow: {message}")
+            }
+            MidasError::InvalidInput(message) => write!(f, "Invalid input: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for MidasError {}
+
+impl From<reqwest::Error> for MidasError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_decode() {
+            MidasError::Decode(error.to_string())
+        } else {
+            MidasError::Network(error.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for MidasError {
+    fn from(error: serde_json::Error) -> Self {
+        MidasError::Decode(error.to_string())
+    }
+}
+
+impl From<std::io::Error> for MidasError {
+    fn from(error: std::io::Error) -> Self {
+        MidasError::Io(error.to_string())
+    }
+}
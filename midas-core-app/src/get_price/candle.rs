@@ -0,0 +1,127 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// One OHLCV bar, `timestamp` is the bar open time in unix milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub timestamp: i64,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+}
+
+impl Candle {
+    /// Candle for feeds that only report a single price per bar (e.g. CoinGecko market chart)
+    pub fn from_price(timestamp: i64, price: Decimal, volume: Decimal) -> Candle {
+        Candle {
+            timestamp,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume,
+        }
+    }
+}
+
+/// Candles sorted by timestamp, the series every backtest runs on
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PriceSeries {
+    candles: Vec<Candle>,
+}
+
+impl PriceSeries {
+    pub fn new(mut candles: Vec<Candle>) -> PriceSeries {
+        candles.sort_by_key(|candle| candle.timestamp);
+        PriceSeries { candles }
+    }
+    pub fn candles(&self) -> &[Candle] {
+        &self.candles
+    }
+    pub fn len(&self) -> usize {
+        self.candles.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.candles.is_empty()
+    }
+    pub fn get(&self, index: usize) -> Option<&Candle> {
+        self.candles.get(index)
+    }
+    pub fn first(&self) -> Option<&Candle> {
+        self.candles.first()
+    }
+    pub fn last(&self) -> Option<&Candle> {
+        self.candles.last()
+    }
+    pub fn timestamps(&self) -> Vec<i64> {
+        self.candles.iter().map(|candle| candle.timestamp).collect()
+    }
+    pub fn opens(&self) -> Box<[Decimal]> {
+        self.candles.iter().map(|candle| candle.open).collect()
+    }
+    pub fn highs(&self) -> Box<[Decimal]> {
+        self.candles.iter().map(|candle| candle.high).collect()
+    }
+    pub fn lows(&self) -> Box<[Decimal]> {
+        self.candles.iter().map(|candle| candle.low).collect()
+    }
+    pub fn closes(&self) -> Box<[Decimal]> {
+        self.candles.iter().map(|candle| candle.close).collect()
+    }
+    pub fn volumes(&self) -> Box<[Decimal]> {
+        self.candles.iter().map(|candle| candle.volume).collect()
+    }
+    /// Index of the first candle at or after `timestamp`
+    pub fn index_of(&self, timestamp: i64) -> usize {
+        self.candles
+            .partition_point(|candle| candle.timestamp < timestamp)
+    }
+    /// Candles with `from <= timestamp <= to`
+    pub fn slice(&self, from: i64, to: i64) -> PriceSeries {
+        let start = self.index_of(from);
+        let end = self
+            .candles
+            .partition_point(|candle| candle.timestamp <= to)
+            .max(start);
+        PriceSeries {
+            candles: self.candles[start..end].to_vec(),
+        }
+    }
+}
+
+impl From<Vec<Candle>> for PriceSeries {
+    fn from(candles: Vec<Candle>) -> PriceSeries {
+        PriceSeries::new(candles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{mock::mock_candles, DAY_IN_MILLISECONDS};
+
+    #[test]
+    fn test_price_series_slice() {
+        let mut candles = mock_candles();
+        candles.reverse();
+        let series = PriceSeries::new(candles);
+
+        let first = series.first().unwrap().timestamp;
+        assert!(series.timestamps().windows(2).all(|pair| pair[0] < pair[1]));
+
+        let sliced = series.slice(
+            first + 2 * DAY_IN_MILLISECONDS,
+            first + 4 * DAY_IN_MILLISECONDS,
+        );
+        assert!(sliced.len() == 3);
+        assert!(sliced.closes()[0] == series.closes()[2]);
+
+        // * Bounds that fall between bars
+        let sliced = series.slice(first + 1, first + 2 * DAY_IN_MILLISECONDS - 1);
+        assert!(sliced.len() == 1);
+
+        assert!(series.slice(first - 10, first - 1).is_empty());
+    }
+}
@@ -0,0 +1,360 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+
+use super::candle::{Candle, PriceSeries};
+use super::historical_price::IHistoricalResponse;
+use super::source::PriceSource;
+use crate::error::MidasError;
+
+const TIMESTAMP_COLUMNS: [&str; 3] = ["timestamp", "time", "date"];
+const PRICE_COLUMNS: [&str; 5] = ["open", "high", "low", "close", "volume"];
+
+/// Load candles from a local `.csv` or `.json` (CoinGecko market chart shape) file
+pub async fn load_candles(path: &Path) -> Result<PriceSeries, MidasError> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| MidasError::Io(format!("Failed to read {}: {e}", path.display())))?;
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("csv") => parse_csv_candles(&content),
+        Some("json") => parse_json_candles(&content),
+        _ => Err(MidasError::InvalidInput(format!(
+            "Unsupported file type: {}",
+            path.display()
+        ))),
+    }
+}
+
+/// CSV must have a header with timestamp (or time / date), open, high, low, close and volume
+/// Columns can be in any order and extra columns are ignored
+pub fn parse_csv_candles(content: &str) -> Result<PriceSeries, MidasError> {
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let header: Vec<String> = match lines.next() {
+        Some((_, line)) => split_csv_line(line)
+            .iter()
+            .map(|column| column.to_lowercase())
+            .collect::<Vec<String>>(),
+        None => return Err(MidasError::Decode("CSV file is empty".to_string())),
+    };
+
+    let timestamp_column: usize = header
+        .iter()
+        .position(|column| TIMESTAMP_COLUMNS.contains(&column.as_str()))
+        .ok_or_else(|| {
+            MidasError::Decode("Missing column `timestamp` in CSV header".to_string())
+        })?;
+    let mut price_columns: [usize; 5] = [0; 5];
+    for (index, name) in PRICE_COLUMNS.iter().enumerate() {
+        price_columns[index] = header
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| MidasError::Decode(format!("Missing column `{name}` in CSV header")))?;
+    }
+
+    let mut candles: Vec<Candle> = Vec::new();
+    for (line_index, line) in lines {
+        let row: Vec<&str> = split_csv_line(line);
+        if row.len() != header.len() {
+            return Err(MidasError::Decode(format!(
+                "Line {}: expected {} columns, found {}",
+                line_index + 1,
+                header.len(),
+                row.len()
+            )));
+        }
+
+        let timestamp = parse_timestamp_millis(row[timestamp_column]).ok_or_else(|| {
+            MidasError::Decode(format!(
+                "Line {}: invalid timestamp `{}`",
+                line_index + 1,
+                row[timestamp_column]
+            ))
+        })?;
+        let mut values: [Decimal; 5] = [Decimal::ZERO; 5];
+        for (index, column) in price_columns.iter().enumerate() {
+            values[index] = parse_decimal(row[*column]).ok_or_else(|| {
+                MidasError::Decode(format!(
+                    "Line {}: invalid number `{}` in column `{}`",
+                    line_index + 1,
+                    row[*column],
+                    PRICE_COLUMNS[index]
+                ))
+            })?;
+        }
+        let [open, high, low, close, volume] = values;
+
+        candles.push(Candle {
+            timestamp,
+            open,
+            high,
+            low,
+            close,
+            volume,
+        });
+    }
+
+    Ok(PriceSeries::new(candles))
+}
+
+pub fn parse_json_candles(content: &str) -> Result<PriceSeries, MidasError> {
+    let response: IHistoricalResponse = serde_json::from_str(content)?;
+
+    Ok(PriceSeries::new(response.extract_candles()))
+}
+
+fn split_csv_line(line: &str) -> Vec<&str> {
+    line.split(',')
+        .map(|value| value.trim().trim_matches('"'))
+        .collect::<Vec<&str>>()
+}
+
+fn parse_decimal(value: &str) -> Option<Decimal> {
+    Decimal::from_str(value)
+        .or_else(|_| Decimal::from_scientific(value))
+        .ok()
+}
+
+/// Parse a timestamp into unix milliseconds
+/// * Unix seconds or milliseconds, e.g. `1640995200` or `1640995200000`
+/// * Date, e.g. `2022-01-01` or `2022/01/01`
+/// * Date and time, e.g. `2022-01-01 12:00:00`, `2022-01-01T12:00:00.250Z` or `2022-01-01T12:00:00+07:00`
+pub fn parse_timestamp(value: &str) -> Result<i64, MidasError> {
+    parse_timestamp_millis(value)
+        .ok_or_else(|| MidasError::Decode(format!("Invalid timestamp `{value}`")))
+}
+
+fn parse_timestamp_millis(value: &str) -> Option<i64> {
+    let value = value.trim();
+
+    if let Ok(number) = value.parse::<i64>() {
+        // * Anything below 10^11 is too small to be milliseconds after 1973
+        return Some(if number.abs() < 100_000_000_000 {
+            number * 1000
+        } else {
+            number
+        });
+    }
+
+    let (date, time) = match value.find(['T', ' ']) {
+        Some(index) => (&value[..index], Some(value[index + 1..].trim())),
+        None => (value, None),
+    };
+
+    let date_parts: Vec<&str> = date.split(['-', '/']).collect();
+    let [year, month, day] = date_parts.as_slice() else {
+        return None;
+    };
+    let year: i64 = year.parse().ok()?;
+    let month: u32 = parse_digits(month)?;
+    let day: u32 = parse_digits(day)?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let time_of_day: i64 = match time {
+        Some(time) => parse_time_of_day(time)?,
+        None => 0,
+    };
+
+    Some(days_from_civil(year, month, day) * 86_400_000 + time_of_day)
+}
+
+/// Digits only, `str::parse` would also take a leading sign
+fn parse_digits(value: &str) -> Option<u32> {
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+/// Milliseconds since midnight UTC, offset included
+fn parse_time_of_day(time: &str) -> Option<i64> {
+    let (clock, offset_minutes): (&str, i64) = if let Some(clock) = time.strip_suffix('Z') {
+        (clock, 0)
+    } else if let Some((clock, offset)) = time
+        .len()
+        .checked_sub(6)
+        .and_then(|start| Some((time.get(..start)?, time.get(start..)?)))
+        .filter(|(clock, offset)| !clock.is_empty() && offset.starts_with(['+', '-']))
+    {
+        let (hours, minutes) = offset[1..].split_once(':')?;
+        let (hours, minutes) = (parse_digits(hours)?, parse_digits(minutes)?);
+        // * No zone is more than 14 hours away from UTC
+        if hours > 14 || minutes > 59 || (hours == 14 && minutes > 0) {
+            return None;
+        }
+        let sign: i64 = if offset.starts_with('-') { -1 } else { 1 };
+        (clock, sign * i64::from(hours * 60 + minutes))
+    } else {
+        (time, 0)
+    };
+
+    let (clock, fraction) = match clock.split_once('.') {
+        Some((clock, fraction)) => (clock, fraction),
+        None => (clock, ""),
+    };
+    let parts: Vec<i64> = clock
+        .split(':')
+        .map(|part| parse_digits(part).map(i64::from))
+        .collect::<Option<Vec<i64>>>()?;
+    let (hour, minute, second) = match parts.as_slice() {
+        [hour, minute] => (*hour, *minute, 0),
+        [hour, minute, second] => (*hour, *minute, *second),
+        _ => return None,
+    };
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let millisecond: i64 = if fraction.is_empty() {
+        0
+    } else if fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        format!("{fraction:0<3}").get(..3)?.parse().ok()?
+    } else {
+        return None;
+    };
+
+    Some(((hour * 60 + minute - offset_minutes) * 60 + second) * 1000 + millisecond)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+/// (http://howardhinnant.github.io/date_algorithms.html#days_from_civil)
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Reads `{directory}/{symbol}.csv`, falling back to `{directory}/{symbol}.json`
+#[derive(Debug, Clone)]
+pub struct FileSource {
+    pub directory: PathBuf,
+}
+
+impl FileSource {
+    pub fn new(directory: impl Into<PathBuf>) -> FileSource {
+        FileSource {
+            directory: directory.into(),
+        }
+    }
+}
+
+impl PriceSource for FileSource {
+    async fn get_candles(
+        &self,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<PriceSeries, MidasError> {
+        let csv_path = self.directory.join(format!("{symbol}.csv"));
+        let path = if csv_path.exists() {
+            csv_path
+        } else {
+            self.directory.join(format!("{symbol}.json"))
+        };
+
+        Ok(load_candles(&path).await?.slice(from, to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert!(parse_timestamp("1640995200") == Ok(1_640_995_200_000));
+        assert!(parse_timestamp("1640995200000") == Ok(1_640_995_200_000));
+        assert!(parse_timestamp("2022-01-01") == Ok(1_640_995_200_000));
+        assert!(parse_timestamp("2022/01/01") == Ok(1_640_995_200_000));
+        assert!(parse_timestamp("2022-01-01 12:30:00") == Ok(1_641_040_200_000));
+        assert!(parse_timestamp("2022-01-01T12:30:00.250Z") == Ok(1_641_040_200_250));
+        assert!(parse_timestamp("2022-01-01T19:30:00+07:00") == Ok(1_641_040_200_000));
+        assert!(parse_timestamp("2022-02-30").is_err());
+        assert!(parse_timestamp("yesterday").is_err());
+        // * Non-ASCII content is rejected, not sliced through
+        assert!(matches!(
+            parse_timestamp("2022-01-01T19:30:00+1é00"),
+            Err(MidasError::Decode(_))
+        ));
+        assert!(matches!(
+            parse_timestamp("2022-01-01T12:30:00.12é"),
+            Err(MidasError::Decode(_))
+        ));
+        assert!(matches!(
+            parse_timestamp("2022-01-01Té2:30:00"),
+            Err(MidasError::Decode(_))
+        ));
+        // * Signs inside the clock and offsets out of range
+        assert!(parse_timestamp("2022-01-01T14:00:00-12:00") == Ok(1_641_088_800_000));
+        for value in [
+            "2022-01-01T-1:-5:00",
+            "2022-01-01T+1:30:00",
+            "2022-01-01T12:30:00.-5",
+            "2022-01-01T12:30:00+15:00",
+            "2022-01-01T12:30:00+14:30",
+            "2022-01-01T12:30:00+01:60",
+            "2022-01-01T12:30:00+-1:00",
+            "2022-+1-01",
+        ] {
+            assert!(matches!(parse_timestamp(value), Err(MidasError::Decode(_))));
+        }
+    }
+
+    #[test]
+    fn test_parse_csv_candles() {
+        let content = "Date,Open,High,Low,Close,Volume\n\
+                       2022-01-02,2,3,1,2.5,100\n\
+                       2022-01-01,1,2.2,0.9,2,1e3\n";
+
+        let candles = parse_csv_candles(content).unwrap();
+
+        assert!(candles.len() == 2);
+        assert!(candles.candles()[0].timestamp == 1_640_995_200_000);
+        assert!(candles.candles()[0].volume == dec!(1000));
+        assert!(candles.candles()[1].close == dec!(2.5));
+
+        let missing_volume = "timestamp,open,high,low,close\n1640995200,1,1,1,1\n";
+        assert!(parse_csv_candles(missing_volume).is_err());
+
+        let bad_row = "timestamp,open,high,low,close,volume\n1640995200,1,one,1,1,1\n";
+        assert!(parse_csv_candles(bad_row).is_err());
+    }
+
+    #[test]
+    fn test_parse_json_candles() {
+        let content = r#"{
+            "prices": [[1640995200000, 1.5], [1641081600000, null]],
+            "market_caps": [[1640995200000, 10], [1641081600000, 12]],
+            "total_volumes": [[1640995200000, 100], [1641081600000, 200]]
+        }"#;
+
+        let candles = parse_json_candles(content).unwrap();
+
+        assert!(candles.len() == 2);
+        assert!(candles.candles()[1].timestamp == 1_641_081_600_000);
+        assert!(candles.candles()[1].close == dec!(1.5));
+        assert!(candles.candles()[1].volume == dec!(200));
+    }
+}
@@ -1,7 +1,13 @@
+use num::ToPrimitive;
 use reqwest;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
+use super::candle::Candle;
+use crate::error::MidasError;
+
+const COINGECKO_URL: &str = "https://api.coingecko.com/api/v3/coins";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IHistoricalResponse {
     prices: Box<[[Option<Decimal>; 2]]>,
@@ -55,26 +61,52 @@ impl IHistoricalResponse {
             })
             .collect::<Box<[Decimal]>>()
     }
+    /// Market chart only has one price per bar, so open, high, low and close are the same value
+    pub fn extract_candles(&self) -> Vec<Candle> {
+        let prices = self.extract_prices();
+        let volumes = self.extract_volumes();
+        self.prices
+            .iter()
+            .enumerate()
+            .filter_map(|(index, [timestamp, _price])| {
+                let timestamp = timestamp.and_then(|value| value.to_i64())?;
+                Some(Candle::from_price(
+                    timestamp,
+                    prices[index],
+                    volumes.get(index).copied().unwrap_or(Decimal::ZERO),
+                ))
+            })
+            .collect::<Vec<Candle>>()
+    }
 }
 
-pub async fn get_historical_chart_data(coin_id: &str) -> IHistoricalResponse {
-    let url: &str = "https://api.coingecko.com/api/v3/coins";
+pub async fn get_historical_chart_data(coin_id: &str) -> Result<IHistoricalResponse, MidasError> {
     let usd_query: &str = "market_chart?vs_currency=usd";
     let day: &str = "9999";
-    let endpoint: String = format!("{url}/{coin_id}/{usd_query}&days={day}&interval=daily");
+    let endpoint: String =
+        format!("{COINGECKO_URL}/{coin_id}/{usd_query}&days={day}&interval=daily");
 
+    fetch_chart_data(endpoint).await
+}
+
+/// `from` and `to` are unix timestamps in seconds, as expected by the range endpoint
+pub async fn get_historical_chart_data_range(
+    coin_id: &str,
+    from: i64,
+    to: i64,
+) -> Result<IHistoricalResponse, MidasError> {
+    let usd_query: &str = "market_chart/range?vs_currency=usd";
+    let endpoint: String = format!("{COINGECKO_URL}/{coin_id}/{usd_query}&from={from}&to={to}");
+
+    fetch_chart_data(endpoint).await
+}
+
+async fn fetch_chart_data(endpoint: String) -> Result<IHistoricalResponse, MidasError> {
     let client = reqwest::Client::new();
 
-    let raw_response = client
-        .get(endpoint)
-        .send()
-        .await
-        .expect("Error at response");
+    let raw_response = client.get(endpoint).send().await?.error_for_status()?;
 
-    match raw_response.json::<IHistoricalResponse>().await {
-        Ok(x) => x,
-        Err(e) => panic!("Error message here: {}", e),
-    }
+    Ok(raw_response.json::<IHistoricalResponse>().await?)
 }
 
 #[cfg(test)]
@@ -83,7 +115,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_historical_data() {
-        let data = get_historical_chart_data("avalanche-2").await;
+        let data = get_historical_chart_data("avalanche-2").await.unwrap();
 
         assert_ne!(data.extract_prices().len(), 0);
     }
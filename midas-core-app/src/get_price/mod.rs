@@ -1,4 +1,7 @@
+pub mod candle;
+pub mod file_price;
 pub mod historical_price;
 pub mod simple_price;
+pub mod source;
 
 // TODO: Get by contract address
@@ -1,34 +1,35 @@
+use std::collections::HashMap;
+
 use reqwest;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
+use crate::error::MidasError;
+
 #[derive(Deserialize, Serialize, Debug)]
 struct Price {
     usd: Decimal,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-struct Avax {
-    #[serde(alias = "avalanche-2")]
-    avalanche: Price,
-}
-
-pub async fn get_current_price(coin_id: &str) -> Decimal {
+pub async fn get_current_price(coin_id: &str) -> Result<Decimal, MidasError> {
     let endpoint: String =
         format!("https://api.coingecko.com/api/v3/simple/price?ids={coin_id}&vs_currencies=usd");
 
     let client: reqwest::Client = reqwest::Client::new();
 
-    let response: Decimal = client
+    // * Response is keyed by coin id, e.g. { "avalanche-2": { "usd": 1.23 } }
+    let response: HashMap<String, Price> = client
         .get(endpoint)
         .send()
-        .await
-        .expect("Error at response")
-        .json::<Avax>()
-        .await
-        .expect("Error on parsing to json")
-        .avalanche
-        .usd;
+        .await?
+        .error_for_status()?
+        .json::<HashMap<String, Price>>()
+        .await?;
 
-    response
+    match response.get(coin_id) {
+        Some(price) => Ok(price.usd),
+        None => Err(MidasError::InvalidInput(format!(
+            "Unknown coin id {coin_id}"
+        ))),
+    }
 }
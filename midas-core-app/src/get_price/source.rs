@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::future::Future;
+
+use super::candle::{Candle, PriceSeries};
+use super::historical_price::get_historical_chart_data_range;
+use crate::error::MidasError;
+
+/// Anything that can provide historical bars for backtesting
+/// `from` and `to` are unix timestamps in milliseconds, both inclusive
+pub trait PriceSource {
+    fn get_candles(
+        &self,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> impl Future<Output = Result<PriceSeries, MidasError>> + Send;
+}
+
+/// Daily bars from the CoinGecko market chart API
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CoinGeckoSource;
+
+impl PriceSource for CoinGeckoSource {
+    async fn get_candles(
+        &self,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<PriceSeries, MidasError> {
+        let candles = get_historical_chart_data_range(symbol, from / 1000, to / 1000)
+            .await?
+            .extract_candles();
+
+        Ok(PriceSeries::new(candles))
+    }
+}
+
+/// Bars kept in memory, keyed by symbol. Useful for tests and offline backtests
+#[derive(Debug, Default, Clone)]
+pub struct InMemorySource {
+    candles: HashMap<String, PriceSeries>,
+}
+
+impl InMemorySource {
+    pub fn new() -> InMemorySource {
+        InMemorySource {
+            candles: HashMap::new(),
+        }
+    }
+    pub fn insert(&mut self, symbol: &str, candles: Vec<Candle>) {
+        self.candles
+            .insert(symbol.to_string(), PriceSeries::new(candles));
+    }
+}
+
+impl PriceSource for InMemorySource {
+    async fn get_candles(
+        &self,
+        symbol: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<PriceSeries, MidasError> {
+        match self.candles.get(symbol) {
+            Some(series) => Ok(series.slice(from, to)),
+            None => Err(MidasError::InvalidInput(format!(
+                "No price data for {symbol}"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::mock::mock_candles;
+
+    #[tokio::test]
+    async fn test_in_memory_source() {
+        let mut source = InMemorySource::new();
+        source.insert("mock", mock_candles());
+
+        let candles = source.get_candles("mock", 0, i64::MAX).await.unwrap();
+        assert!(candles.len() == 30);
+
+        let from = candles.candles()[5].timestamp;
+        let to = candles.candles()[9].timestamp;
+        let ranged = source.get_candles("mock", from, to).await.unwrap();
+        assert!(ranged.len() == 5);
+        assert!(ranged.first() == candles.get(5));
+
+        assert!(source.get_candles("unknown", 0, i64::MAX).await.is_err());
+    }
+}
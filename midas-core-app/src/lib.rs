@@ -1,6 +1,9 @@
 pub mod backtest;
+pub mod error;
 pub mod get_price;
 pub mod price_manipulation;
 pub mod risk_management;
 pub mod ta_rs;
 pub mod utils;
+
+pub use error::MidasError;
@@ -0,0 +1,149 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::error::MidasError;
+
+/// One fall from a peak until the value gets back to it
+/// Indices refer to the analysed values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IDrawdownEpisode {
+    pub peak_index: usize,
+    pub trough_index: usize,
+    /// First index back at or above the peak, `None` while still under water
+    pub recovery_index: Option<usize>,
+    /// Fall from peak to trough, `-0.2` is 20% below the peak
+    pub depth: Decimal,
+    /// Values from the peak to the recovery, or to the last value when not recovered
+    pub length: usize,
+    /// Values from the trough to the recovery
+    pub time_to_recover: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IDrawdownAnalysis {
+    /// Fall of every value from its running peak, zero on new highs
+    pub underwater: Vec<Decimal>,
+    /// Deepest episodes first
+    pub episodes: Vec<IDrawdownEpisode>,
+}
+
+pub fn get_underwater_curve(values: &[Decimal]) -> Result<Vec<Decimal>, MidasError> {
+    let mut peak = match values.first() {
+        Some(value) => *value,
+        None => return Ok(Vec::new()),
+    };
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            peak = peak.max(*value);
+            (value - peak)
+                .checked_div(peak)
+                .ok_or_else(|| MidasError::ArithmeticOverflow(format!("drawdown at {index}")))
+        })
+        .collect()
+}
+
+/// Every drawdown episode in chronological order
+pub fn get_drawdown_episodes(values: &[Decimal]) -> Result<Vec<IDrawdownEpisode>, MidasError> {
+    let underwater = get_underwater_curve(values)?;
+    let mut episodes: Vec<IDrawdownEpisode> = Vec::new();
+    let mut current: Option<IDrawdownEpisode> = None;
+    for (index, drawdown) in underwater.iter().enumerate() {
+        match current.as_mut() {
+            None if *drawdown < Decimal::ZERO => {
+                current = Some(IDrawdownEpisode {
+                    peak_index: index - 1,
+                    trough_index: index,
+                    recovery_index: None,
+                    depth: *drawdown,
+                    length: 1,
+                    time_to_recover: None,
+                })
+            }
+            None => (),
+            Some(episode) if drawdown.is_zero() => {
+                episode.recovery_index = Some(index);
+                episode.length = index - episode.peak_index;
+                episode.time_to_recover = Some(index - episode.trough_index);
+                episodes.push(*episode);
+                current = None;
+            }
+            Some(episode) => {
+                episode.length = index - episode.peak_index;
+                if *drawdown < episode.depth {
+                    episode.depth = *drawdown;
+                    episode.trough_index = index;
+                }
+            }
+        }
+    }
+    episodes.extend(current);
+    Ok(episodes)
+}
+
+/// Underwater curve and the `top` deepest episodes
+pub fn get_drawdown_analysis(
+    values: &[Decimal],
+    top: usize,
+) -> Result<IDrawdownAnalysis, MidasError> {
+    let mut episodes = get_drawdown_episodes(values)?;
+    episodes.sort_by_key(|episode| episode.depth);
+    episodes.truncate(top);
+    Ok(IDrawdownAnalysis {
+        underwater: get_underwater_curve(values)?,
+        episodes,
+    })
+}
+
+/// Longest time under water, zero when the values never fall
+pub fn get_longest_drawdown_duration(values: &[Decimal]) -> Result<usize, MidasError> {
+    Ok(get_drawdown_episodes(values)?
+        .iter()
+        .map(|episode| episode.length)
+        .max()
+        .unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_drawdown_episodes() {
+        let values = [
+            dec!(100),
+            dec!(90),
+            dec!(80),
+            dec!(95),
+            dec!(100),
+            dec!(120),
+            dec!(108),
+            dec!(114),
+        ];
+
+        let analysis = get_drawdown_analysis(&values, 1).unwrap();
+        let episodes = get_drawdown_episodes(&values).unwrap();
+
+        assert!(analysis.underwater[2] == dec!(-0.2));
+        assert!(analysis.underwater[5] == Decimal::ZERO);
+        assert!(episodes.len() == 2);
+        assert!(
+            episodes[0]
+                == IDrawdownEpisode {
+                    peak_index: 0,
+                    trough_index: 2,
+                    recovery_index: Some(4),
+                    depth: dec!(-0.2),
+                    length: 4,
+                    time_to_recover: Some(2),
+                }
+        );
+        assert!(episodes[1].recovery_index.is_none());
+        assert!(episodes[1].depth == dec!(-0.1));
+        assert!(episodes[1].length == 2);
+        assert!(analysis.episodes == vec![episodes[0]]);
+        assert!(get_longest_drawdown_duration(&values).unwrap() == 4);
+    }
+}
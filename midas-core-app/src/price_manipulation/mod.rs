@@ -1,2 +1,3 @@
+pub mod drawdown;
 pub mod returns;
 pub mod risks;
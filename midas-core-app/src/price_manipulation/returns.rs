@@ -1,23 +1,50 @@
+use crate::error::MidasError;
 use crate::utils::calculate_return;
 use crate::utils::mean;
 use num::FromPrimitive;
 use rust_decimal::prelude::Decimal;
 
-pub async fn get_return(prices: &[Decimal]) -> Vec<Decimal> {
+pub async fn get_return(prices: &[Decimal]) -> Result<Vec<Decimal>, MidasError> {
+    calculate_returns(prices)
+}
+
+/// Synchronous `get_return`, for code that is not async like the backtest engine
+pub fn calculate_returns(prices: &[Decimal]) -> Result<Vec<Decimal>, MidasError> {
+    if prices.len() < 2 {
+        return Err(MidasError::InsufficientData {
+            required: 2,
+            available: prices.len(),
+        });
+    }
+    if let Some(index) = prices.iter().position(|price| price.is_zero()) {
+        return Err(MidasError::ArithmeticOverflow(format!(
+            "Zero price at index {index}, return is undefined"
+        )));
+    }
     let prices_one_lag = prices[1..].to_vec();
     let prices_len = &prices_one_lag.len();
 
     let result = calculate_return(prices, &prices_one_lag, *prices_len);
 
-    if let Some(x) = result {
-        x
-    } else {
-        Vec::new()
-    }
+    Ok(result.unwrap_or_default())
+}
+
+pub async fn get_average_return(returns: Vec<Decimal>) -> Result<Decimal, MidasError> {
+    calculate_average_return(&returns)
 }
 
-pub async fn get_average_return(returns: Vec<Decimal>) -> Decimal {
-    mean(&returns, &Decimal::from_usize(returns.len()).unwrap())
+pub fn calculate_average_return(returns: &[Decimal]) -> Result<Decimal, MidasError> {
+    if returns.is_empty() {
+        return Err(MidasError::InsufficientData {
+            required: 1,
+            available: 0,
+        });
+    }
+    let length = Decimal::from_usize(returns.len()).ok_or_else(|| {
+        MidasError::ArithmeticOverflow(format!("{} returns as Decimal", returns.len()))
+    })?;
+
+    Ok(mean(returns, &length))
 }
 
 #[cfg(test)]
@@ -38,7 +65,7 @@ mod tests {
         ];
 
         assert!(
-            get_return(&prices).await
+            get_return(&prices).await.unwrap()
                 == vec![
                     dec!(0.124559932942162615255658005),
                     dec!(-0.1180679785330948121645796064),
@@ -60,8 +87,26 @@ mod tests {
             dec!(54.11),
         ];
 
-        let returns = get_return(&prices).await;
+        let returns = get_return(&prices).await.unwrap();
 
-        assert!(get_average_return(returns).await == dec!(-0.0162705147970854752825101970));
+        assert!(
+            get_average_return(returns).await.unwrap() == dec!(-0.0162705147970854752825101970)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_return_errors() {
+        assert!(
+            get_return(&[dec!(1)]).await
+                == Err(MidasError::InsufficientData {
+                    required: 2,
+                    available: 1,
+                })
+        );
+        assert!(matches!(
+            get_return(&[dec!(1), dec!(0), dec!(1)]).await,
+            Err(MidasError::ArithmeticOverflow(_))
+        ));
+        assert!(get_average_return(Vec::new()).await.is_err());
     }
 }
@@ -1,24 +1,41 @@
-use crate::price_manipulation::returns::get_average_return;
+use crate::error::MidasError;
+use crate::price_manipulation::returns::calculate_average_return;
 use rust_decimal::{prelude::Decimal, MathematicalOps};
 
 use num::FromPrimitive;
 
-pub async fn get_variance(returns: Vec<Decimal>) -> Decimal {
-    let avg_return = get_average_return(returns.clone()).await;
-    let squared_deviation: Decimal = returns.iter().map(|x| (x - avg_return).powu(2)).sum();
-    let length = Decimal::from_usize(returns.len()).unwrap();
+pub async fn get_variance(returns: Vec<Decimal>) -> Result<Decimal, MidasError> {
+    calculate_variance(&returns)
+}
+
+/// Synchronous `get_variance`, for code that is not async like the backtest engine
+pub fn calculate_variance(returns: &[Decimal]) -> Result<Decimal, MidasError> {
+    let avg_return = calculate_average_return(returns)?;
+    let squared_deviation: Decimal = returns
+        .iter()
+        .map(|x| (x - avg_return).checked_powu(2))
+        .sum::<Option<Decimal>>()
+        .ok_or_else(|| MidasError::ArithmeticOverflow("squared deviation".to_string()))?;
+    let length = Decimal::from_usize(returns.len()).ok_or_else(|| {
+        MidasError::ArithmeticOverflow(format!("{} returns as Decimal", returns.len()))
+    })?;
 
-    squared_deviation / length
+    Ok(squared_deviation / length)
 }
 
 // TODO: Should add more method except from standard deviation
 
-pub async fn get_volatility(returns: Vec<Decimal>) -> Decimal {
-    let variance = get_variance(returns).await;
+pub async fn get_volatility(returns: Vec<Decimal>) -> Result<Decimal, MidasError> {
+    calculate_volatility(&returns)
+}
+
+/// Synchronous `get_volatility`
+pub fn calculate_volatility(returns: &[Decimal]) -> Result<Decimal, MidasError> {
+    let variance = calculate_variance(returns)?;
     if let Some(volatility) = variance.sqrt() {
-        volatility
+        Ok(volatility)
     } else {
-        Decimal::ZERO
+        Ok(Decimal::ZERO)
     }
 }
 
@@ -39,9 +56,9 @@ mod tests {
             dec!(54.11),
         ];
 
-        let returns = get_return(&prices).await;
+        let returns = get_return(&prices).await.unwrap();
 
-        assert!(get_variance(returns).await == dec!(0.0061673295653075877308743126));
+        assert!(get_variance(returns).await.unwrap() == dec!(0.0061673295653075877308743126));
     }
 
     #[tokio::test]
@@ -55,10 +72,11 @@ mod tests {
             dec!(54.11),
         ];
 
-        let returns = get_return(&prices).await;
+        let returns = get_return(&prices).await.unwrap();
 
         assert!(
-            get_volatility(returns).await == dec!(0.0061673295653075877308743126).sqrt().unwrap()
+            get_volatility(returns).await.unwrap()
+                == dec!(0.0061673295653075877308743126).sqrt().unwrap()
         );
     }
 }
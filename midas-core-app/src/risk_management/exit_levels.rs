@@ -0,0 +1,179 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Distance of an exit level from the reference price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopLevel {
+    /// Distance in price, e.g. 1.5 means 1.5 below a 20 entry for a long stop
+    Fixed(Decimal),
+    /// Distance as a fraction of price, e.g. 0.05 for 5%
+    Percent(Decimal),
+}
+
+impl StopLevel {
+    pub fn distance(&self, price: Decimal) -> Decimal {
+        match self {
+            StopLevel::Fixed(distance) => *distance,
+            StopLevel::Percent(percent) => price * *percent,
+        }
+    }
+    pub fn below(&self, price: Decimal) -> Decimal {
+        price - self.distance(price)
+    }
+    pub fn above(&self, price: Decimal) -> Decimal {
+        price + self.distance(price)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IExitRules {
+    pub stop_loss: Option<StopLevel>,
+    pub take_profit: Option<StopLevel>,
+    /// Follows the best price since entry, a long trails below the highest high
+    pub trailing_stop: Option<StopLevel>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExitReason {
+    Signal,
+    StopLoss,
+    TakeProfit,
+    TrailingStop,
+    /// Closed at the last close of the data, see `EndOfData::ForceClose`
+    EndOfData,
+}
+
+/// Exit levels of one open position
+/// Levels are placed from the `StopLevel` distances of `IExitRules`: `calculate_risk_reward` takes
+/// the stop and target as inputs and only derives a quantity and a ratio from them, which
+/// `PositionSizing::FixedRisk` does through `calculate_optimal_quantity` once the stop is known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IExitLevels {
+    pub is_long: bool,
+    pub stop_loss: Option<Decimal>,
+    pub take_profit: Option<Decimal>,
+    pub trailing_stop: Option<StopLevel>,
+    /// Highest high for a long or lowest low for a short since entry
+    pub extreme: Decimal,
+}
+
+impl IExitLevels {
+    pub fn new(rules: &IExitRules, entry: Decimal, is_long: bool) -> IExitLevels {
+        let (stop_loss, take_profit) = if is_long {
+            (
+                rules.stop_loss.map(|level| level.below(entry)),
+                rules.take_profit.map(|level| level.above(entry)),
+            )
+        } else {
+            (
+                rules.stop_loss.map(|level| level.above(entry)),
+                rules.take_profit.map(|level| level.below(entry)),
+            )
+        };
+        IExitLevels {
+            is_long,
+            stop_loss,
+            take_profit,
+            trailing_stop: rules.trailing_stop,
+            extreme: entry,
+        }
+    }
+    pub fn trailing_level(&self) -> Option<Decimal> {
+        self.trailing_stop.map(|level| {
+            if self.is_long {
+                level.below(self.extreme)
+            } else {
+                level.above(self.extreme)
+            }
+        })
+    }
+    /// Exit price and reason if the bar crosses a level, a bar that opens beyond the level fills at the open
+    /// When a bar crosses both the stop and the target, the stop is assumed to be hit first
+    pub fn check(
+        &self,
+        open: Decimal,
+        high: Decimal,
+        low: Decimal,
+    ) -> Option<(Decimal, ExitReason)> {
+        let stops = [
+            (self.stop_loss, ExitReason::StopLoss),
+            (self.trailing_level(), ExitReason::TrailingStop),
+        ];
+        for (level, reason) in stops {
+            match level {
+                Some(level) if self.is_long && low <= level => {
+                    return Some((open.min(level), reason))
+                }
+                Some(level) if !self.is_long && high >= level => {
+                    return Some((open.max(level), reason))
+                }
+                _ => (),
+            }
+        }
+        match self.take_profit {
+            Some(level) if self.is_long && high >= level => {
+                Some((open.max(level), ExitReason::TakeProfit))
+            }
+            Some(level) if !self.is_long && low <= level => {
+                Some((open.min(level), ExitReason::TakeProfit))
+            }
+            _ => None,
+        }
+    }
+    /// Move the trailing reference after a bar has been checked
+    pub fn update(&mut self, high: Decimal, low: Decimal) {
+        self.extreme = if self.is_long {
+            self.extreme.max(high)
+        } else {
+            self.extreme.min(low)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_exit_levels() {
+        let rules = IExitRules {
+            stop_loss: Some(StopLevel::Percent(dec!(0.1))),
+            take_profit: Some(StopLevel::Fixed(dec!(5))),
+            trailing_stop: None,
+        };
+
+        let long = IExitLevels::new(&rules, dec!(20), true);
+        assert!(long.stop_loss == Some(dec!(18)));
+        assert!(long.take_profit == Some(dec!(25)));
+        assert!(long.check(dec!(20), dec!(24), dec!(19)).is_none());
+        assert!(long.check(dec!(20), dec!(21), dec!(17)) == Some((dec!(18), ExitReason::StopLoss)));
+        // * Gap below the stop fills at the open
+        assert!(long.check(dec!(16), dec!(17), dec!(15)) == Some((dec!(16), ExitReason::StopLoss)));
+        assert!(
+            long.check(dec!(20), dec!(26), dec!(19)) == Some((dec!(25), ExitReason::TakeProfit))
+        );
+
+        let short = IExitLevels::new(&rules, dec!(20), false);
+        assert!(short.stop_loss == Some(dec!(22)));
+        assert!(short.take_profit == Some(dec!(15)));
+        assert!(
+            short.check(dec!(20), dec!(23), dec!(19)) == Some((dec!(22), ExitReason::StopLoss))
+        );
+    }
+
+    #[test]
+    fn test_trailing_stop() {
+        let rules = IExitRules {
+            trailing_stop: Some(StopLevel::Percent(dec!(0.1))),
+            ..IExitRules::default()
+        };
+
+        let mut long = IExitLevels::new(&rules, dec!(20), true);
+        long.update(dec!(30), dec!(21));
+        assert!(long.trailing_level() == Some(dec!(27)));
+        assert!(
+            long.check(dec!(29), dec!(29), dec!(26)) == Some((dec!(27), ExitReason::TrailingStop))
+        );
+    }
+}
@@ -1 +1,2 @@
+pub mod exit_levels;
 pub mod risk_reward_ratio;
@@ -34,7 +34,7 @@ pub fn calculate_risk_reward<T: Sub<Output = T> + Mul<Output = T> + Div<Output =
     }
 }
 
-fn calculate_optimal_quantity<T: Sub<Output = T> + Mul<Output = T> + Div<Output = T>>(
+pub fn calculate_optimal_quantity<T: Sub<Output = T> + Mul<Output = T> + Div<Output = T>>(
     risk_per_trade: T,
     entry: T,
     stop_loss: T,
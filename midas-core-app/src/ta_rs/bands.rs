@@ -0,0 +1,441 @@
+use std::{
+    fmt::Debug,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use num::FromPrimitive;
+use rust_decimal::{Decimal, MathematicalOps};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::MidasError,
+    ta_rs::{
+        indicator::{Ema, Indicator, Sma},
+        ma::{check_period, from_u32},
+    },
+};
+
+/// Square root for the standard deviation of the Bollinger Bands, `None` for negative values
+pub trait SquareRoot: Sized {
+    fn square_root(&self) -> Option<Self>;
+}
+
+impl SquareRoot for Decimal {
+    fn square_root(&self) -> Option<Self> {
+        self.sqrt()
+    }
+}
+
+impl SquareRoot for f64 {
+    fn square_root(&self) -> Option<Self> {
+        (*self >= 0.0).then(|| self.sqrt())
+    }
+}
+
+/// Upper, middle and lower band, all aligned so the last values belong to the last bar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IBands<T> {
+    pub upper: Vec<T>,
+    pub middle: Vec<T>,
+    pub lower: Vec<T>,
+    /// Position of the close in the bands, 0 on the lower band and 1 on the upper band
+    pub percent_b: Vec<T>,
+    /// Width of the bands relative to the middle band
+    pub bandwidth: Vec<T>,
+}
+
+impl<T: FromPrimitive + Sub<Output = T> + Div<Output = T> + PartialEq + Copy> IBands<T> {
+    /// `closes` are the last closes, one per band value
+    /// Flat bands put the close in the middle, a zero middle band gives a zero bandwidth
+    fn new(
+        upper: Vec<T>,
+        middle: Vec<T>,
+        lower: Vec<T>,
+        closes: &[T],
+    ) -> Result<IBands<T>, MidasError> {
+        let zero = from_u32::<T>(0)?;
+        let half = from_u32::<T>(1)? / from_u32::<T>(2)?;
+        let closes = &closes[closes.len() - middle.len()..];
+        let percent_b = (0..middle.len())
+            .map(|index| {
+                let width = upper[index] - lower[index];
+                if width == zero {
+                    half
+                } else {
+                    (closes[index] - lower[index]) / width
+                }
+            })
+            .collect();
+        let bandwidth = (0..middle.len())
+            .map(|index| {
+                if middle[index] == zero {
+                    zero
+                } else {
+                    (upper[index] - lower[index]) / middle[index]
+                }
+            })
+            .collect();
+        Ok(IBands {
+            upper,
+            middle,
+            lower,
+            percent_b,
+            bandwidth,
+        })
+    }
+}
+
+impl<T> IBands<T> {
+    pub fn len(&self) -> usize {
+        self.middle.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.middle.is_empty()
+    }
+}
+
+fn check_lengths<T>(highs: &[T], lows: &[T], closes: &[T]) -> Result<(), MidasError> {
+    if highs.len() != closes.len() || lows.len() != closes.len() {
+        return Err(MidasError::InvalidInput(format!(
+            "{} highs, {} lows and {} closes",
+            highs.len(),
+            lows.len(),
+            closes.len()
+        )));
+    }
+    Ok(())
+}
+
+/// SMA of `period` plus and minus `deviations` population standard deviations of the same window
+pub fn get_bollinger_bands<
+    T: FromPrimitive
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + SquareRoot
+        + PartialOrd
+        + Copy
+        + Debug,
+>(
+    prices: &[T],
+    period: u32,
+    deviations: T,
+) -> Result<IBands<T>, MidasError> {
+    let window = check_period(prices.len(), period)?;
+    let length = from_u32::<T>(period)?;
+    let zero = from_u32::<T>(0)?;
+    let mut sma = Sma::new(period)?;
+    let middle: Vec<T> = prices
+        .iter()
+        .filter_map(|price| sma.update(*price))
+        .collect();
+
+    let mut upper: Vec<T> = Vec::with_capacity(middle.len());
+    let mut lower: Vec<T> = Vec::with_capacity(middle.len());
+    for (mean, prices) in middle.iter().zip(prices.windows(window)) {
+        let variance = prices.iter().fold(zero, |sum, price| {
+            let deviation = *price - *mean;
+            sum + deviation * deviation
+        }) / length;
+        let deviation = variance.square_root().ok_or_else(|| {
+            MidasError::ArithmeticOverflow(format!("Square root of {variance:?}"))
+        })? * deviations;
+        upper.push(*mean + deviation);
+        lower.push(*mean - deviation);
+    }
+
+    IBands::new(upper, middle, lower, prices)
+}
+
+/// Average true range with Wilder's smoothing, `atr[0]` belongs to bar `period`
+/// The true range of a bar needs the previous close, so the first bar has none
+pub fn get_atr<
+    T: FromPrimitive
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + PartialOrd
+        + Copy
+        + Debug,
+>(
+    highs: &[T],
+    lows: &[T],
+    closes: &[T],
+    period: u32,
+) -> Result<Vec<T>, MidasError> {
+    check_lengths(highs, lows, closes)?;
+    let window = check_period(closes.len().saturating_sub(1), period)?;
+    let zero = from_u32::<T>(0)?;
+    let length = from_u32::<T>(period)?;
+    let previous_weight = from_u32::<T>(period - 1)?;
+    let distance = |a: T, b: T| if a > b { a - b } else { b - a };
+
+    let true_ranges: Vec<T> = (1..closes.len())
+        .map(|index| {
+            let previous = closes[index - 1];
+            [
+                highs[index] - lows[index],
+                distance(highs[index], previous),
+                distance(lows[index], previous),
+            ]
+            .into_iter()
+            .fold(
+                zero,
+                |highest, range| if range > highest { range } else { highest },
+            )
+        })
+        .collect();
+    let mut average = true_ranges[..window]
+        .iter()
+        .fold(zero, |sum, range| sum + *range)
+        / length;
+
+    let mut atr: Vec<T> = vec![average];
+    for range in &true_ranges[window..] {
+        average = (average * previous_weight + *range) / length;
+        atr.push(average);
+    }
+    Ok(atr)
+}
+
+/// EMA of `ema_period` closes plus and minus `multiplier` ATRs of `atr_period`
+pub fn get_keltner_channels<
+    T: FromPrimitive
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + PartialOrd
+        + Copy
+        + Debug,
+>(
+    highs: &[T],
+    lows: &[T],
+    closes: &[T],
+    ema_period: u32,
+    atr_period: u32,
+    multiplier: T,
+) -> Result<IBands<T>, MidasError> {
+    check_period(closes.len(), ema_period)?;
+    let atr = get_atr(highs, lows, closes, atr_period)?;
+    let mut ema = Ema::new(ema_period)?;
+    let ema: Vec<T> = closes
+        .iter()
+        .filter_map(|close| ema.update(*close))
+        .collect();
+
+    // * Both end on the last bar, keep the shorter one
+    let length = ema.len().min(atr.len());
+    let middle = ema[ema.len() - length..].to_vec();
+    let atr = &atr[atr.len() - length..];
+    let upper = middle
+        .iter()
+        .zip(atr)
+        .map(|(mean, range)| *mean + *range * multiplier)
+        .collect();
+    let lower = middle
+        .iter()
+        .zip(atr)
+        .map(|(mean, range)| *mean - *range * multiplier)
+        .collect();
+
+    IBands::new(upper, middle, lower, closes)
+}
+
+/// Highest high and lowest low of the `period` bars before each bar, `channel[0]` belongs to bar
+/// `period`
+/// The current bar is left out so a close can break out of the channel
+pub fn get_donchian_channels<
+    T: FromPrimitive + Add<Output = T> + Sub<Output = T> + Div<Output = T> + PartialOrd + Copy + Debug,
+>(
+    highs: &[T],
+    lows: &[T],
+    closes: &[T],
+    period: u32,
+) -> Result<IBands<T>, MidasError> {
+    check_lengths(highs, lows, closes)?;
+    let window = check_period(closes.len().saturating_sub(1), period)?;
+    let two = from_u32::<T>(2)?;
+
+    let mut upper: Vec<T> = Vec::with_capacity(closes.len() - window);
+    let mut lower: Vec<T> = Vec::with_capacity(closes.len() - window);
+    for index in window..closes.len() {
+        let start = index - window;
+        let (highest, lowest) = (start..index).fold((highs[start], lows[start]), |(h, l), i| {
+            (
+                if highs[i] > h { highs[i] } else { h },
+                if lows[i] < l { lows[i] } else { l },
+            )
+        });
+        upper.push(highest);
+        lower.push(lowest);
+    }
+    let middle = upper
+        .iter()
+        .zip(&lower)
+        .map(|(highest, lowest)| (*highest + *lowest) / two)
+        .collect();
+
+    IBands::new(upper, middle, lower, closes)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BandSignal {
+    /// Long when the close goes above the upper band, short when it goes below the lower band
+    Breakout,
+    /// Long when the close comes back above the lower band, short when it comes back below the
+    /// upper band
+    ReEntry,
+}
+
+/// Bands turned into a vector for `IBackTestingIndicator::get_positions`, aligned on `bands`
+/// Positive while long, negative while short, starts negative like `get_threshold_cross`
+pub fn get_band_signals<T: FromPrimitive + PartialOrd + Copy + Debug>(
+    closes: &[T],
+    bands: &IBands<T>,
+    signal: BandSignal,
+) -> Result<Vec<T>, MidasError> {
+    if closes.len() < bands.len() {
+        return Err(MidasError::InsufficientData {
+            required: bands.len(),
+            available: closes.len(),
+        });
+    }
+    let long = T::from_i8(1)
+        .ok_or_else(|| MidasError::ArithmeticOverflow("Failed to convert 1".to_string()))?;
+    let short = T::from_i8(-1)
+        .ok_or_else(|| MidasError::ArithmeticOverflow("Failed to convert -1".to_string()))?;
+    let closes = &closes[closes.len() - bands.len()..];
+
+    let mut state = short;
+    let mut signals: Vec<T> = Vec::with_capacity(closes.len());
+    for (index, close) in closes.iter().enumerate() {
+        match signal {
+            BandSignal::Breakout => {
+                if *close > bands.upper[index] {
+                    state = long;
+                } else if *close < bands.lower[index] {
+                    state = short;
+                }
+            }
+            BandSignal::ReEntry => {
+                if let Some(previous) = index.checked_sub(1) {
+                    if closes[previous] < bands.lower[previous] && *close >= bands.lower[index] {
+                        state = long;
+                    } else if closes[previous] > bands.upper[previous]
+                        && *close <= bands.upper[index]
+                    {
+                        state = short;
+                    }
+                }
+            }
+        }
+        signals.push(state);
+    }
+    Ok(signals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ta_rs::ma::{exponential_moving_average, simple_moving_average, IMAParams};
+    use crate::utils::mock::mock_prices;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_bollinger_bands() {
+        let prices = [dec!(2), dec!(4), dec!(6), dec!(6)];
+
+        let bands = get_bollinger_bands(&prices, 2, dec!(2)).unwrap();
+
+        // * Standard deviations 1, 1, 0
+        assert!(bands.middle == vec![dec!(3), dec!(5), dec!(6)]);
+        assert!(bands.upper == vec![dec!(5), dec!(7), dec!(6)]);
+        assert!(bands.lower == vec![dec!(1), dec!(3), dec!(6)]);
+        assert!(bands.percent_b == vec![dec!(0.75), dec!(0.75), dec!(0.5)]);
+        assert!(bands.bandwidth == vec![dec!(4) / dec!(3), dec!(0.8), dec!(0)]);
+        let mock = mock_prices();
+        let sma = simple_moving_average(IMAParams {
+            prices: &mock,
+            period: 20,
+        })
+        .unwrap();
+        assert!(get_bollinger_bands(&mock, 20, dec!(2)).unwrap().middle == sma);
+    }
+
+    #[test]
+    fn test_keltner_and_donchian_channels() {
+        let highs = [dec!(10), dec!(12), dec!(14), dec!(13), dec!(12)];
+        let lows = [dec!(8), dec!(9), dec!(11), dec!(10), dec!(8)];
+        let closes = [dec!(9), dec!(11), dec!(13), dec!(11), dec!(9)];
+
+        // * True ranges 3, 3, 3, 4
+        assert!(get_atr(&highs, &lows, &closes, 2).unwrap() == vec![dec!(3), dec!(3), dec!(3.5)]);
+        let keltner = get_keltner_channels(&highs, &lows, &closes, 3, 2, dec!(2)).unwrap();
+        let ema = exponential_moving_average(IMAParams {
+            prices: &closes,
+            period: 3,
+        })
+        .unwrap();
+        assert!(keltner.middle == ema);
+        // * EMA 3: 11 (seed), (11 + 11) / 2, (9 + 11) / 2 with ATRs 3, 3, 3.5
+        assert!(keltner.middle == vec![dec!(11), dec!(11), dec!(10)]);
+        assert!(keltner.upper == vec![dec!(17), dec!(17), dec!(17)]);
+        assert!(keltner.lower == vec![dec!(5), dec!(5), dec!(3)]);
+
+        let donchian = get_donchian_channels(&highs, &lows, &closes, 3).unwrap();
+        assert!(donchian.upper == vec![dec!(14), dec!(14)]);
+        assert!(donchian.lower == vec![dec!(8), dec!(9)]);
+        assert!(donchian.middle == vec![dec!(11), dec!(11.5)]);
+        assert!(get_donchian_channels(&highs, &lows[1..], &closes, 3).is_err());
+    }
+
+    #[test]
+    fn test_band_signals() {
+        let closes = [
+            dec!(5),
+            dec!(12),
+            dec!(8),
+            dec!(0),
+            dec!(3),
+            dec!(11),
+            dec!(9),
+        ];
+        let bands = IBands::new(
+            vec![dec!(10); 7],
+            vec![dec!(5); 7],
+            vec![dec!(2); 7],
+            &closes,
+        )
+        .unwrap();
+
+        let breakout = get_band_signals(&closes, &bands, BandSignal::Breakout).unwrap();
+        assert!(
+            breakout
+                == vec![
+                    dec!(-1),
+                    dec!(1),
+                    dec!(1),
+                    dec!(-1),
+                    dec!(-1),
+                    dec!(1),
+                    dec!(1)
+                ]
+        );
+        let re_entry = get_band_signals(&closes, &bands, BandSignal::ReEntry).unwrap();
+        assert!(
+            re_entry
+                == vec![
+                    dec!(-1),
+                    dec!(-1),
+                    dec!(-1),
+                    dec!(-1),
+                    dec!(1),
+                    dec!(1),
+                    dec!(-1)
+                ]
+        );
+    }
+}
@@ -5,12 +5,177 @@ use std::{
 };
 
 use num::FromPrimitive;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    ta_rs::ma::{exponential_moving_average, IMAParams},
+    error::MidasError,
+    ta_rs::{
+        indicator::{Ema, Indicator},
+        ma::{check_fast_slow, check_period, exponential_moving_average, from_u32, IMAParams},
+    },
     utils::create_indicator_cross_vec,
 };
 
+/// Zone of a bar, from the smoothed price against the fast and slow EMAs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CdcZone {
+    /// Fast above slow, price above fast
+    Green,
+    /// Fast above slow, price between slow and fast
+    Yellow,
+    /// Fast above slow, price below slow
+    Orange,
+    /// Fast below slow, price below fast
+    Red,
+    /// Fast below slow, price between fast and slow
+    LightBlue,
+    /// Fast below slow, price above slow
+    Blue,
+}
+
+impl CdcZone {
+    fn new<T: PartialOrd>(price: &T, fast: &T, slow: &T) -> CdcZone {
+        if fast > slow {
+            if price > fast {
+                CdcZone::Green
+            } else if price < slow {
+                CdcZone::Orange
+            } else {
+                CdcZone::Yellow
+            }
+        } else if price < fast {
+            CdcZone::Red
+        } else if price > slow {
+            CdcZone::Blue
+        } else {
+            CdcZone::LightBlue
+        }
+    }
+    /// Fast EMA above slow EMA
+    pub fn is_bullish(&self) -> bool {
+        matches!(self, CdcZone::Green | CdcZone::Yellow | CdcZone::Orange)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CdcAction {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ICdcActionZoneParams {
+    /// EMA applied to OHLC4 before the fast and slow EMAs, 1 keeps the raw OHLC4
+    pub smoothing: u32,
+    pub fast: u32,
+    pub slow: u32,
+}
+
+impl Default for ICdcActionZoneParams {
+    /// CDC Action Zone V3: EMA 2 of OHLC4, then EMA 12 / 26
+    fn default() -> Self {
+        ICdcActionZoneParams {
+            smoothing: 2,
+            fast: 12,
+            slow: 26,
+        }
+    }
+}
+
+/// Every series aligned on the slow EMA, the last values belong to the last bar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ICdcActionZone<T> {
+    /// Smoothed OHLC4
+    pub price: Vec<T>,
+    pub fast: Vec<T>,
+    pub slow: Vec<T>,
+    pub zones: Vec<CdcZone>,
+    /// Buy on the first green bar after a sell, sell on the first red bar after a buy
+    pub actions: Vec<Option<CdcAction>>,
+}
+
+/// Buy / sell rules of the original indicator
+/// A buy is the first green bar since the last sell, a sell the first red bar since the last buy,
+/// other zones (e.g. blue after a red bar) only warn and keep the current side
+pub fn get_cdc_actions(zones: &[CdcZone]) -> Vec<Option<CdcAction>> {
+    let mut side: Option<CdcAction> = None;
+    zones
+        .iter()
+        .map(|zone| {
+            let action = match zone {
+                CdcZone::Green => Some(CdcAction::Buy),
+                CdcZone::Red => Some(CdcAction::Sell),
+                _ => None,
+            };
+            if action.is_none() || action == side {
+                return None;
+            }
+            side = action;
+            action
+        })
+        .collect()
+}
+
+/// CDC Action Zone as drawn on TradingView, from OHLC bars
+pub fn get_cdc_action_zone_with_zones<
+    T: FromPrimitive
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + PartialOrd
+        + Copy
+        + Debug,
+>(
+    opens: &[T],
+    highs: &[T],
+    lows: &[T],
+    closes: &[T],
+    params: &ICdcActionZoneParams,
+) -> Result<ICdcActionZone<T>, MidasError> {
+    if opens.len() != closes.len() || highs.len() != closes.len() || lows.len() != closes.len() {
+        return Err(MidasError::InvalidInput(format!(
+            "{} opens, {} highs, {} lows and {} closes",
+            opens.len(),
+            highs.len(),
+            lows.len(),
+            closes.len()
+        )));
+    }
+    check_fast_slow(params.fast, params.slow)?;
+    let smoothing = check_period(closes.len(), params.smoothing)?;
+    check_period(closes.len() + 1 - smoothing, params.slow)?;
+    let four = from_u32::<T>(4)?;
+    let mut price_ema = Ema::new(params.smoothing)?;
+    let mut fast_ema = Ema::new(params.fast)?;
+    let mut slow_ema = Ema::new(params.slow)?;
+
+    let mut result = ICdcActionZone {
+        price: vec![],
+        fast: vec![],
+        slow: vec![],
+        zones: vec![],
+        actions: vec![],
+    };
+    for index in 0..closes.len() {
+        let ohlc4 = (opens[index] + highs[index] + lows[index] + closes[index]) / four;
+        let Some(price) = price_ema.update(ohlc4) else {
+            continue;
+        };
+        let fast = fast_ema.update(price);
+        let (Some(fast), Some(slow)) = (fast, slow_ema.update(price)) else {
+            continue;
+        };
+        result.zones.push(CdcZone::new(&price, &fast, &slow));
+        result.price.push(price);
+        result.fast.push(fast);
+        result.slow.push(slow);
+    }
+    result.actions = get_cdc_actions(&result.zones);
+
+    Ok(result)
+}
+
 pub async fn get_cdc_action_zone<
     'a,
     T: 'a
@@ -24,36 +189,55 @@ pub async fn get_cdc_action_zone<
         + Debug,
 >(
     prices: &'a [T],
-) -> Result<Vec<T>, String> {
-    let ema_12: Vec<T> =
-        if let Ok(value) = exponential_moving_average(IMAParams { prices, period: 12 }) {
-            value
-        } else {
-            panic!("Error at EMA12 calculation")
-        };
+) -> Result<Vec<T>, MidasError> {
+    get_cdc_action_zone_with_periods(prices, 12, 26)
+}
 
-    let ema_26: Vec<T> =
-        if let Ok(value) = exponential_moving_average(IMAParams { prices, period: 26 }) {
-            value
-        } else {
-            panic!("Error at EMA26 calculation")
-        };
-    Ok(create_indicator_cross_vec(&ema_12, &ema_26))
+/// Fast EMA minus slow EMA, aligned on the slow EMA
+pub fn get_cdc_action_zone_with_periods<
+    'a,
+    T: 'a
+        + FromPrimitive
+        + Sum<&'a T>
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + Copy
+        + Debug,
+>(
+    prices: &'a [T],
+    fast: u32,
+    slow: u32,
+) -> Result<Vec<T>, MidasError> {
+    check_fast_slow(fast, slow)?;
+    let ema_fast: Vec<T> = exponential_moving_average(IMAParams {
+        prices,
+        period: fast,
+    })?;
+    let ema_slow: Vec<T> = exponential_moving_average(IMAParams {
+        prices,
+        period: slow,
+    })?;
+
+    Ok(create_indicator_cross_vec(&ema_fast, &ema_slow))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::utils::mock::mock_prices;
+    use rust_decimal::Decimal;
     use rust_decimal_macros::dec;
 
     #[tokio::test]
     async fn test_cdc_action_zone() {
         let result = [
-            dec![2.862388889492811017275605213],
-            dec![2.989937212871300315078043333],
-            dec![3.088501429616597624580488659],
-            dec![3.141695600233379304394877577],
+            dec![2.449225351406152923344125776],
+            dec![2.535038150729598167221492355],
+            dec![2.678680222555891755248244037],
+            dec![2.793400589684117309910248983],
+            dec![2.862615685414959356327372529],
         ];
 
         if let Ok(value) = get_cdc_action_zone(&mock_prices()).await {
@@ -62,4 +246,130 @@ mod tests {
             panic!("Failed");
         }
     }
+
+    #[test]
+    fn test_cdc_zones() {
+        let prices = mock_prices();
+
+        // * Flat bars make OHLC4 the close, no smoothing leaves the plain EMAs
+        let params = ICdcActionZoneParams {
+            smoothing: 1,
+            fast: 12,
+            slow: 26,
+        };
+        let cdc =
+            get_cdc_action_zone_with_zones(&prices, &prices, &prices, &prices, &params).unwrap();
+        let cross = get_cdc_action_zone_with_periods(&prices, 12, 26).unwrap();
+        assert!(cdc.zones.len() == cross.len());
+        assert!(cdc.price == prices[25..]);
+        for (index, zone) in cdc.zones.iter().enumerate() {
+            assert!(zone.is_bullish() == (cross[index] > Decimal::ZERO));
+            assert!(cdc.fast[index] - cdc.slow[index] == cross[index]);
+        }
+
+        let smoothed = get_cdc_action_zone_with_zones(
+            &prices,
+            &prices,
+            &prices,
+            &prices,
+            &ICdcActionZoneParams::default(),
+        )
+        .unwrap();
+        assert!(smoothed.zones.len() == 4);
+        assert!(
+            get_cdc_action_zone_with_zones(&prices, &prices[1..], &prices, &prices, &params)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_cdc_zones_by_hand() {
+        let closes = [
+            dec!(10),
+            dec!(12),
+            dec!(14),
+            dec!(13),
+            dec!(9),
+            dec!(8),
+            dec!(12),
+        ];
+        let params = ICdcActionZoneParams {
+            smoothing: 2,
+            fast: 2,
+            slow: 3,
+        };
+
+        let cdc =
+            get_cdc_action_zone_with_zones(&closes, &closes, &closes, &closes, &params).unwrap();
+
+        // * EMA 2 of the closes: 11 (SMA seed), 13, 13, 31 / 3, 79 / 9, 295 / 27
+        // * EMA 2 of that: 12 (seed), 38 / 3, 100 / 9, 86 / 9, 848 / 81
+        // * EMA 3 of that: 37 / 3 (seed), 34 / 3, 181 / 18, 1133 / 108
+        let round = |values: &[Decimal]| -> Vec<Decimal> {
+            values.iter().map(|value| value.round_dp(10)).collect()
+        };
+        assert!(
+            round(&cdc.price)
+                == round(&[
+                    dec!(13),
+                    dec!(31) / dec!(3),
+                    dec!(79) / dec!(9),
+                    dec!(295) / dec!(27)
+                ])
+        );
+        assert!(
+            round(&cdc.fast)
+                == round(&[
+                    dec!(38) / dec!(3),
+                    dec!(100) / dec!(9),
+                    dec!(86) / dec!(9),
+                    dec!(848) / dec!(81)
+                ])
+        );
+        assert!(
+            round(&cdc.slow)
+                == round(&[
+                    dec!(37) / dec!(3),
+                    dec!(34) / dec!(3),
+                    dec!(181) / dec!(18),
+                    dec!(1133) / dec!(108)
+                ])
+        );
+        assert!(cdc.zones == vec![CdcZone::Green, CdcZone::Red, CdcZone::Red, CdcZone::Blue]);
+        assert!(cdc.actions == vec![Some(CdcAction::Buy), Some(CdcAction::Sell), None, None]);
+    }
+
+    #[test]
+    fn test_cdc_actions() {
+        let zones = [
+            CdcZone::Yellow,
+            CdcZone::Green,
+            CdcZone::Green,
+            CdcZone::Orange,
+            CdcZone::Blue,
+            CdcZone::Green,
+            CdcZone::Red,
+            CdcZone::LightBlue,
+            CdcZone::Red,
+            CdcZone::Green,
+        ];
+
+        assert!(
+            get_cdc_actions(&zones)
+                == vec![
+                    None,
+                    Some(CdcAction::Buy),
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(CdcAction::Sell),
+                    None,
+                    None,
+                    Some(CdcAction::Buy)
+                ]
+        );
+        assert!(CdcZone::new(&dec!(9), &dec!(10), &dec!(8)) == CdcZone::Yellow);
+        assert!(CdcZone::new(&dec!(9), &dec!(8), &dec!(10)) == CdcZone::LightBlue);
+    }
 }
@@ -0,0 +1,257 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Sub};
+
+use num::FromPrimitive;
+
+use crate::error::MidasError;
+use crate::ta_rs::ma::{check_fast_slow, check_period, from_u32};
+
+/// Indicator fed one bar at a time, every update is O(1)
+/// Outputs match the batch function of the same name, bar by bar once warmed up
+pub trait Indicator {
+    type Input;
+    type Output;
+    /// Value on the bar of `input`, `None` while warming up
+    fn update(&mut self, input: Self::Input) -> Option<Self::Output>;
+    /// Forget every bar seen so far
+    fn reset(&mut self);
+}
+
+/// Streaming `simple_moving_average`, keeps a running sum of the window
+#[derive(Debug, Clone)]
+pub struct Sma<T> {
+    period: usize,
+    length: T,
+    zero: T,
+    sum: T,
+    window: VecDeque<T>,
+}
+
+impl<T: FromPrimitive + Add<Output = T> + Sub<Output = T> + Div<Output = T> + Copy + Debug> Sma<T> {
+    pub fn new(period: u32) -> Result<Sma<T>, MidasError> {
+        let window = check_period(usize::MAX, period)?;
+        let zero = from_u32::<T>(0)?;
+        Ok(Sma {
+            period: window,
+            length: from_u32(period)?,
+            zero,
+            sum: zero,
+            window: VecDeque::with_capacity(window),
+        })
+    }
+}
+
+impl<T: FromPrimitive + Add<Output = T> + Sub<Output = T> + Div<Output = T> + Copy + Debug>
+    Indicator for Sma<T>
+{
+    type Input = T;
+    type Output = T;
+    fn update(&mut self, price: T) -> Option<T> {
+        self.window.push_back(price);
+        self.sum = self.sum + price;
+        if self.window.len() > self.period {
+            let oldest = self.window.pop_front()?;
+            self.sum = self.sum - oldest;
+        }
+        (self.window.len() == self.period).then(|| self.sum / self.length)
+    }
+    fn reset(&mut self) {
+        self.window.clear();
+        self.sum = self.zero;
+    }
+}
+
+/// Streaming `exponential_moving_average`, seeded with the SMA of the first `period` prices
+#[derive(Debug, Clone)]
+pub struct Ema<T> {
+    seed: Sma<T>,
+    smoothing_factor: T,
+    one: T,
+    value: Option<T>,
+}
+
+impl<
+        T: FromPrimitive
+            + Add<Output = T>
+            + Sub<Output = T>
+            + Mul<Output = T>
+            + Div<Output = T>
+            + Copy
+            + Debug,
+    > Ema<T>
+{
+    pub fn new(period: u32) -> Result<Ema<T>, MidasError> {
+        Ok(Ema {
+            seed: Sma::new(period)?,
+            smoothing_factor: from_u32::<T>(2)? / from_u32::<T>(period.saturating_add(1))?,
+            one: from_u32(1)?,
+            value: None,
+        })
+    }
+}
+
+impl<
+        T: FromPrimitive
+            + Add<Output = T>
+            + Sub<Output = T>
+            + Mul<Output = T>
+            + Div<Output = T>
+            + Copy
+            + Debug,
+    > Indicator for Ema<T>
+{
+    type Input = T;
+    type Output = T;
+    fn update(&mut self, price: T) -> Option<T> {
+        self.value = match self.value {
+            Some(previous) => Some(
+                (self.smoothing_factor * price) + (self.one - self.smoothing_factor) * previous,
+            ),
+            None => self.seed.update(price),
+        };
+        self.value
+    }
+    fn reset(&mut self) {
+        self.seed.reset();
+        self.value = None;
+    }
+}
+
+/// Streaming `get_ma_cross`, fast SMA minus slow SMA
+#[derive(Debug, Clone)]
+pub struct MaCross<T> {
+    fast: Sma<T>,
+    slow: Sma<T>,
+}
+
+impl<T: FromPrimitive + Add<Output = T> + Sub<Output = T> + Div<Output = T> + Copy + Debug>
+    MaCross<T>
+{
+    pub fn new(fast: u32, slow: u32) -> Result<MaCross<T>, MidasError> {
+        check_fast_slow(fast, slow)?;
+        Ok(MaCross {
+            fast: Sma::new(fast)?,
+            slow: Sma::new(slow)?,
+        })
+    }
+}
+
+impl<T: FromPrimitive + Add<Output = T> + Sub<Output = T> + Div<Output = T> + Copy + Debug>
+    Indicator for MaCross<T>
+{
+    type Input = T;
+    type Output = T;
+    fn update(&mut self, price: T) -> Option<T> {
+        let fast = self.fast.update(price);
+        let slow = self.slow.update(price)?;
+        Some(fast? - slow)
+    }
+    fn reset(&mut self) {
+        self.fast.reset();
+        self.slow.reset();
+    }
+}
+
+/// Streaming `get_cdc_action_zone_with_periods`, fast EMA minus slow EMA
+#[derive(Debug, Clone)]
+pub struct CdcActionZone<T> {
+    fast: Ema<T>,
+    slow: Ema<T>,
+}
+
+impl<
+        T: FromPrimitive
+            + Add<Output = T>
+            + Sub<Output = T>
+            + Mul<Output = T>
+            + Div<Output = T>
+            + Copy
+            + Debug,
+    > CdcActionZone<T>
+{
+    pub fn new(fast: u32, slow: u32) -> Result<CdcActionZone<T>, MidasError> {
+        check_fast_slow(fast, slow)?;
+        Ok(CdcActionZone {
+            fast: Ema::new(fast)?,
+            slow: Ema::new(slow)?,
+        })
+    }
+}
+
+impl<
+        T: FromPrimitive
+            + Add<Output = T>
+            + Sub<Output = T>
+            + Mul<Output = T>
+            + Div<Output = T>
+            + Copy
+            + Debug,
+    > Indicator for CdcActionZone<T>
+{
+    type Input = T;
+    type Output = T;
+    fn update(&mut self, price: T) -> Option<T> {
+        let fast = self.fast.update(price);
+        let slow = self.slow.update(price)?;
+        Some(fast? - slow)
+    }
+    fn reset(&mut self) {
+        self.fast.reset();
+        self.slow.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ta_rs::cdc_action_zone::get_cdc_action_zone_with_periods;
+    use crate::ta_rs::ma::{
+        exponential_moving_average, get_ma_cross, simple_moving_average, IMAParams,
+    };
+    use crate::utils::mock::mock_prices;
+    use rust_decimal::Decimal;
+
+    fn stream<I: Indicator<Input = Decimal, Output = Decimal>>(
+        indicator: &mut I,
+        prices: &[Decimal],
+    ) -> Vec<Decimal> {
+        prices
+            .iter()
+            .filter_map(|price| indicator.update(*price))
+            .collect()
+    }
+
+    #[test]
+    fn test_streaming_matches_batch() {
+        let prices = mock_prices();
+        let params = || IMAParams {
+            prices: &prices,
+            period: 5,
+        };
+
+        let mut sma = Sma::new(5).unwrap();
+        assert!(stream(&mut sma, &prices) == simple_moving_average(params()).unwrap());
+        let mut ema = Ema::new(5).unwrap();
+        assert!(stream(&mut ema, &prices) == exponential_moving_average(params()).unwrap());
+        let mut ma_cross = MaCross::new(3, 7).unwrap();
+        assert!(stream(&mut ma_cross, &prices) == get_ma_cross(&prices, 3, 7).unwrap());
+        let mut cdc = CdcActionZone::new(12, 26).unwrap();
+        let batch = get_cdc_action_zone_with_periods(&prices, 12, 26).unwrap();
+        assert!(stream(&mut cdc, &prices) == batch);
+
+        // * Warm up again after a reset
+        cdc.reset();
+        assert!(cdc.update(prices[0]).is_none());
+        assert!(stream(&mut cdc, &prices[1..]) == batch);
+    }
+
+    #[test]
+    fn test_indicator_errors() {
+        assert!(Sma::<Decimal>::new(0).is_err());
+        assert!(matches!(
+            CdcActionZone::<Decimal>::new(26, 12),
+            Err(MidasError::InvalidPeriod(_))
+        ));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::MidasError;
 use crate::utils::{create_indicator_cross_vec, mean};
 use num::{FromPrimitive, ToPrimitive};
 use std::ops::{Add, Div, Mul, Sub};
@@ -7,7 +8,39 @@ pub struct IMAParams<'a, T: 'a + FromPrimitive + Sum<&'a T> + Div<Output = T> +
     pub period: u32,
 }
 
-// TODO: Custom Error
+/// Period must be positive and not longer than the data, returns the period as `usize`
+pub(crate) fn check_period(length: usize, period: u32) -> Result<usize, MidasError> {
+    if period == 0 {
+        return Err(MidasError::InvalidPeriod(
+            "period must be greater than zero".to_string(),
+        ));
+    }
+    let period: usize = period
+        .to_usize()
+        .ok_or_else(|| MidasError::ArithmeticOverflow(format!("period {period} as usize")))?;
+    if length < period {
+        return Err(MidasError::InsufficientData {
+            required: period,
+            available: length,
+        });
+    }
+    Ok(period)
+}
+
+/// Fast period must not be longer than the slow one
+pub(crate) fn check_fast_slow(fast: u32, slow: u32) -> Result<(), MidasError> {
+    if fast > slow {
+        return Err(MidasError::InvalidPeriod(format!(
+            "Fast period {fast} is longer than slow period {slow}"
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn from_u32<T: FromPrimitive>(value: u32) -> Result<T, MidasError> {
+    T::from_u32(value)
+        .ok_or_else(|| MidasError::ArithmeticOverflow(format!("Failed to convert {value}")))
+}
 
 fn calculate_simple_moving_average<
     'a,
@@ -15,8 +48,8 @@ fn calculate_simple_moving_average<
 >(
     prices: &'a [T],
     period: u32,
-) -> Option<T> {
-    Some(mean(prices, &T::from_u32(period).unwrap()))
+) -> Result<T, MidasError> {
+    Ok(mean(prices, &from_u32(period)?))
 }
 
 pub fn simple_moving_average<
@@ -24,23 +57,21 @@ pub fn simple_moving_average<
     T: 'a + FromPrimitive + Sum<&'a T> + Div<Output = T> + Copy + Debug,
 >(
     params: IMAParams<'a, T>,
-) -> Result<Vec<T>, String> {
+) -> Result<Vec<T>, MidasError> {
     let IMAParams { prices, period } = params;
     let max_length = prices.len();
+    let window = check_period(max_length, period)?;
 
     let mut sma: Vec<T> = Vec::new();
 
     for index in 0..max_length {
-        if index + period.to_usize().unwrap() > max_length {
+        if index + window > max_length {
             break;
         }
-        if let Some(value) =
-            calculate_simple_moving_average(&prices[index..index + period as usize], period)
-        {
-            sma.push(value);
-        } else {
-            panic!("Error in calculate SMA")
-        }
+        sma.push(calculate_simple_moving_average(
+            &prices[index..index + window],
+            period,
+        )?);
     }
 
     Ok(sma)
@@ -65,10 +96,11 @@ fn calculate_exponential_moving_average<
     price: T,
     smoothing_factor: T,
     prev_ema: T,
-) -> Option<T> {
-    Some((smoothing_factor * price) + (T::from_i16(1).unwrap() - smoothing_factor) * prev_ema)
+) -> Result<T, MidasError> {
+    Ok((smoothing_factor * price) + (from_u32::<T>(1)? - smoothing_factor) * prev_ema)
 }
 
+/// Seeded with the SMA of the first `period` prices, then one value per price like `simple_moving_average`
 pub fn exponential_moving_average<
     'a,
     T: 'a
@@ -82,25 +114,23 @@ pub fn exponential_moving_average<
         + Debug,
 >(
     params: IMAParams<'a, T>,
-) -> Result<Vec<T>, String> {
+) -> Result<Vec<T>, MidasError> {
     let IMAParams { prices, period } = params;
 
     let max_length = prices.len();
-    let first_ema = calculate_simple_moving_average(&prices[0..period as usize], period)
-        .expect("Failed to calculate SMA");
-    let smoothing_factor = T::from_u32(2).unwrap() / T::from_u32(1 + period).unwrap();
+    let window = check_period(max_length, period)?;
+    let first_ema = calculate_simple_moving_average(&prices[0..window], period)?;
+    let smoothing_factor = from_u32::<T>(2)? / from_u32::<T>(period.saturating_add(1))?;
 
     let mut ema: Vec<T> = vec![first_ema];
 
-    for price in prices.iter().take(max_length).skip(period as usize + 1) {
-        let prev_ema = ema.last().expect("No previous EMA");
-        if let Some(value) =
-            calculate_exponential_moving_average(*price, smoothing_factor, *prev_ema)
-        {
-            ema.push(value);
-        } else {
-            panic!("Error in calculate EMA")
-        }
+    for price in prices.iter().take(max_length).skip(window) {
+        let prev_ema = ema[ema.len() - 1];
+        ema.push(calculate_exponential_moving_average(
+            *price,
+            smoothing_factor,
+            prev_ema,
+        )?);
     }
 
     Ok(ema)
@@ -113,23 +143,16 @@ pub fn get_ma_cross<
     prices: &'a [T],
     fast: u32,
     slow: u32,
-) -> Result<Vec<T>, String> {
-    let fast_ma: Vec<T> = if let Ok(value) = simple_moving_average(IMAParams {
+) -> Result<Vec<T>, MidasError> {
+    check_fast_slow(fast, slow)?;
+    let fast_ma: Vec<T> = simple_moving_average(IMAParams {
         prices,
         period: fast,
-    }) {
-        value
-    } else {
-        panic!("Error in calculate Fast SMA")
-    };
-    let slow_ma: Vec<T> = if let Ok(value) = simple_moving_average(IMAParams {
+    })?;
+    let slow_ma: Vec<T> = simple_moving_average(IMAParams {
         prices,
         period: slow,
-    }) {
-        value
-    } else {
-        panic!("Error in calculate Slow SMA")
-    };
+    })?;
     let max_length = slow_ma.len();
     let fast_ma_cutoff = fast_ma.len() - max_length;
     Ok(create_indicator_cross_vec(
@@ -183,12 +206,13 @@ mod tests {
     fn test_exponential_moving_average() {
         let result = [
             dec![3],
-            dec![6.3333333333333333333333333335],
-            dec![10.111111111111111111111111111],
-            dec![12.703703703703703703703703704],
-            dec![14.901234567901234567901234568],
-            dec![16.967078189300411522633744856],
-            dec![18.989026063100137174211248285],
+            dec![5.0000000000000000000000000001],
+            dec![7.0000000000000000000000000001],
+            dec![10.333333333333333333333333333],
+            dec![12.777777777777777777777777778],
+            dec![14.925925925925925925925925926],
+            dec![16.975308641975308641975308642],
+            dec![18.991769547325102880658436214],
         ];
 
         if let Ok(value) = exponential_moving_average(IMAParams {
@@ -201,4 +225,29 @@ mod tests {
             panic!("Failed")
         }
     }
+
+    #[test]
+    fn test_moving_average_errors() {
+        assert!(
+            simple_moving_average(IMAParams {
+                prices: &PRICES,
+                period: 0,
+            }) == Err(MidasError::InvalidPeriod(
+                "period must be greater than zero".to_string()
+            ))
+        );
+        assert!(
+            exponential_moving_average(IMAParams {
+                prices: &PRICES,
+                period: 10,
+            }) == Err(MidasError::InsufficientData {
+                required: 10,
+                available: 9,
+            })
+        );
+        assert!(matches!(
+            get_ma_cross(&PRICES, 5, 3),
+            Err(MidasError::InvalidPeriod(_))
+        ));
+    }
 }
@@ -0,0 +1,227 @@
+use std::{
+    fmt::Debug,
+    iter::Sum,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use num::FromPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::MidasError,
+    ta_rs::{
+        indicator::{Ema, Indicator, Sma},
+        ma::{check_fast_slow, check_period},
+    },
+    utils::create_indicator_cross_vec,
+};
+
+/// Moving average used to smooth a series
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MaType {
+    Sma,
+    #[default]
+    Ema,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IMACDParams {
+    pub fast: u32,
+    pub slow: u32,
+    /// Period of the signal line, a moving average of the MACD line
+    pub signal: u32,
+    /// Moving average of the fast and slow lines
+    pub oscillator_ma: MaType,
+    pub signal_ma: MaType,
+}
+
+impl Default for IMACDParams {
+    /// 12 / 26 / 9 with EMAs everywhere
+    fn default() -> Self {
+        IMACDParams {
+            fast: 12,
+            slow: 26,
+            signal: 9,
+            oscillator_ma: MaType::Ema,
+            signal_ma: MaType::Ema,
+        }
+    }
+}
+
+/// Series aligned on the signal line, the last values belong to the last bar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IMACD<T> {
+    /// Fast average minus slow average
+    pub line: Vec<T>,
+    pub signal: Vec<T>,
+    /// Line minus signal, it crosses zero whenever the line crosses the signal line
+    pub histogram: Vec<T>,
+}
+
+/// `period` moving average of every value from the first full window on
+fn smooth<
+    T: FromPrimitive
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + Copy
+        + Debug,
+>(
+    values: &[T],
+    period: u32,
+    ma: MaType,
+) -> Result<Vec<T>, MidasError> {
+    check_period(values.len(), period)?;
+    Ok(match ma {
+        MaType::Sma => {
+            let mut sma = Sma::new(period)?;
+            values
+                .iter()
+                .filter_map(|value| sma.update(*value))
+                .collect()
+        }
+        MaType::Ema => {
+            let mut ema = Ema::new(period)?;
+            values
+                .iter()
+                .filter_map(|value| ema.update(*value))
+                .collect()
+        }
+    })
+}
+
+/// Fast average minus slow average, aligned on the slow one
+/// With EMAs this is `get_cdc_action_zone_with_periods`
+pub fn get_macd_line<
+    'a,
+    T: 'a
+        + FromPrimitive
+        + Sum<&'a T>
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + Copy
+        + Debug,
+>(
+    prices: &'a [T],
+    fast: u32,
+    slow: u32,
+    ma: MaType,
+) -> Result<Vec<T>, MidasError> {
+    check_fast_slow(fast, slow)?;
+    Ok(create_indicator_cross_vec(
+        &smooth(prices, fast, ma)?,
+        &smooth(prices, slow, ma)?,
+    ))
+}
+
+pub fn get_macd<
+    'a,
+    T: 'a
+        + FromPrimitive
+        + Sum<&'a T>
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + Copy
+        + Debug,
+>(
+    prices: &'a [T],
+    params: &IMACDParams,
+) -> Result<IMACD<T>, MidasError> {
+    let line = get_macd_line(prices, params.fast, params.slow, params.oscillator_ma)?;
+    let signal = smooth(&line, params.signal, params.signal_ma)?;
+    let histogram = create_indicator_cross_vec(&line, &signal);
+    Ok(IMACD {
+        line: line[line.len() - signal.len()..].to_vec(),
+        signal,
+        histogram,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ta_rs::cdc_action_zone::get_cdc_action_zone_with_periods;
+    use crate::ta_rs::ma::get_ma_cross;
+    use crate::utils::mock::mock_prices;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_macd() {
+        let prices = mock_prices();
+        let params = IMACDParams {
+            fast: 3,
+            slow: 6,
+            signal: 4,
+            ..IMACDParams::default()
+        };
+
+        let macd = get_macd(&prices, &params).unwrap();
+
+        // * 25 line values, 22 signal values
+        assert!(macd.signal.len() == 22);
+        assert!(macd.line.len() == macd.signal.len());
+        assert!(macd.histogram.len() == macd.signal.len());
+        assert!(macd.histogram[5] == macd.line[5] - macd.signal[5]);
+        let cdc = get_cdc_action_zone_with_periods(&prices, 3, 6).unwrap();
+        assert!(macd.line == cdc[3..]);
+        let sma = get_macd_line(&prices, 3, 6, MaType::Sma).unwrap();
+        assert!(sma == get_ma_cross(&prices, 3, 6).unwrap());
+    }
+
+    #[test]
+    fn test_macd_by_hand() {
+        let prices = [dec!(2), dec!(4), dec!(6), dec!(8), dec!(10), dec!(8)];
+        let params = IMACDParams {
+            fast: 2,
+            slow: 3,
+            signal: 2,
+            ..IMACDParams::default()
+        };
+
+        let macd = get_macd(&prices, &params).unwrap();
+
+        // * EMA 2: 3 (seed), 5, 7, 9, 25 / 3 and EMA 3: 4 (seed), 6, 8, 8
+        // * Line 1, 1, 1, 1 / 3 and its EMA 2: 1 (seed), 1, 5 / 9
+        let round = |values: &[Decimal]| -> Vec<Decimal> {
+            values.iter().map(|value| value.round_dp(10)).collect()
+        };
+        assert!(round(&macd.line) == round(&[dec!(1), dec!(1), dec!(1) / dec!(3)]));
+        assert!(round(&macd.signal) == round(&[dec!(1), dec!(1), dec!(5) / dec!(9)]));
+        assert!(round(&macd.histogram) == round(&[dec!(0), dec!(0), dec!(-2) / dec!(9)]));
+    }
+
+    #[test]
+    fn test_macd_errors() {
+        let prices = [dec!(1), dec!(2), dec!(3), dec!(4)];
+
+        assert!(matches!(
+            get_macd(
+                &prices,
+                &IMACDParams {
+                    fast: 3,
+                    slow: 2,
+                    ..IMACDParams::default()
+                }
+            ),
+            Err(MidasError::InvalidPeriod(_))
+        ));
+        // * 3 line values are not enough for a signal of 9
+        assert!(matches!(
+            get_macd(
+                &prices,
+                &IMACDParams {
+                    fast: 1,
+                    slow: 2,
+                    ..IMACDParams::default()
+                }
+            ),
+            Err(MidasError::InsufficientData { .. })
+        ));
+    }
+}
@@ -1,2 +1,7 @@
+pub mod bands;
 pub mod cdc_action_zone;
+pub mod indicator;
 pub mod ma;
+pub mod macd;
+pub mod rsi;
+pub mod stochastic;
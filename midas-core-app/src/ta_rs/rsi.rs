@@ -0,0 +1,158 @@
+use std::{
+    fmt::Debug,
+    iter::Sum,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use num::FromPrimitive;
+
+use crate::{
+    error::MidasError,
+    ta_rs::ma::{check_period, from_u32},
+};
+
+fn calculate_rsi<T: Add<Output = T> + Sub<Output = T> + Div<Output = T> + PartialEq + Copy>(
+    average_gain: T,
+    average_loss: T,
+    [zero, one, hundred]: [T; 3],
+) -> T {
+    if average_loss == zero {
+        return hundred;
+    }
+    hundred - hundred / (one + average_gain / average_loss)
+}
+
+/// Relative strength index with Wilder's smoothing, from 0 to 100
+/// `rsi[0]` belongs to bar `period`, the first `period` changes seed the average gain and loss
+pub fn get_rsi<
+    'a,
+    T: 'a
+        + FromPrimitive
+        + Sum<&'a T>
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + PartialOrd
+        + Copy
+        + Debug,
+>(
+    prices: &'a [T],
+    period: u32,
+) -> Result<Vec<T>, MidasError> {
+    let window = check_period(prices.len().saturating_sub(1), period)?;
+    let zero = from_u32::<T>(0)?;
+    let constants = [zero, from_u32::<T>(1)?, from_u32::<T>(100)?];
+    let length = from_u32::<T>(period)?;
+    let previous_weight = from_u32::<T>(period - 1)?;
+
+    // * Gain and loss of every bar, both positive
+    let changes: Vec<(T, T)> = prices
+        .windows(2)
+        .map(|pair| {
+            let change = pair[1] - pair[0];
+            if change > zero {
+                (change, zero)
+            } else {
+                (zero, zero - change)
+            }
+        })
+        .collect();
+    let (mut average_gain, mut average_loss) = changes[..window]
+        .iter()
+        .fold((zero, zero), |(gain, loss), change| {
+            (gain + change.0, loss + change.1)
+        });
+    average_gain = average_gain / length;
+    average_loss = average_loss / length;
+
+    let mut rsi: Vec<T> = vec![calculate_rsi(average_gain, average_loss, constants)];
+    for (gain, loss) in &changes[window..] {
+        average_gain = (average_gain * previous_weight + *gain) / length;
+        average_loss = (average_loss * previous_weight + *loss) / length;
+        rsi.push(calculate_rsi(average_gain, average_loss, constants));
+    }
+
+    Ok(rsi)
+}
+
+/// Oscillator turned into a vector for `IBackTestingIndicator::get_positions`:
+/// positive from a cross back above `oversold`, negative from a cross back below `overbought`
+/// Starts negative so the first long waits for an oversold bounce
+pub fn get_threshold_cross<T: FromPrimitive + PartialOrd + Copy + Debug>(
+    values: &[T],
+    oversold: T,
+    overbought: T,
+) -> Result<Vec<T>, MidasError> {
+    if oversold > overbought {
+        return Err(MidasError::InvalidInput(format!(
+            "Oversold {oversold:?} is above overbought {overbought:?}"
+        )));
+    }
+    let long = T::from_i8(1)
+        .ok_or_else(|| MidasError::ArithmeticOverflow("Failed to convert 1".to_string()))?;
+    let short = T::from_i8(-1)
+        .ok_or_else(|| MidasError::ArithmeticOverflow("Failed to convert -1".to_string()))?;
+
+    let mut state = short;
+    let mut crossed: Vec<T> = Vec::with_capacity(values.len());
+    for (index, value) in values.iter().enumerate() {
+        if let Some(previous) = index.checked_sub(1).map(|previous| values[previous]) {
+            if previous <= oversold && *value > oversold {
+                state = long;
+            } else if previous >= overbought && *value < overbought {
+                state = short;
+            }
+        }
+        crossed.push(state);
+    }
+    Ok(crossed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_rsi() {
+        let prices = [dec!(1), dec!(2), dec!(1), dec!(2), dec!(1)];
+
+        // * Gains 0.5 / losses 0.5, then (0.5 + 1) / 2 against 0.5 / 2, then 0.375 against 0.625
+        assert!(get_rsi(&prices, 2).unwrap() == vec![dec!(50), dec!(75), dec!(37.5)]);
+        assert!(get_rsi(&[dec!(1), dec!(2), dec!(3)], 2).unwrap() == vec![dec!(100)]);
+        assert!(matches!(
+            get_rsi(&prices, 5),
+            Err(MidasError::InsufficientData { .. })
+        ));
+    }
+
+    #[test]
+    fn test_threshold_cross() {
+        let rsi = [
+            dec!(40),
+            dec!(25),
+            dec!(35),
+            dec!(60),
+            dec!(75),
+            dec!(65),
+            dec!(50),
+        ];
+
+        let crossed = get_threshold_cross(&rsi, dec!(30), dec!(70)).unwrap();
+
+        assert!(
+            crossed
+                == vec![
+                    dec!(-1),
+                    dec!(-1),
+                    dec!(1),
+                    dec!(1),
+                    dec!(1),
+                    dec!(-1),
+                    dec!(-1)
+                ]
+        );
+        assert!(get_threshold_cross(&rsi, dec!(70), dec!(30)).is_err());
+    }
+}
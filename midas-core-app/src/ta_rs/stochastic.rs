@@ -0,0 +1,201 @@
+use std::{
+    fmt::Debug,
+    iter::Sum,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use num::FromPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::MidasError,
+    ta_rs::{
+        ma::{check_period, from_u32},
+        rsi::get_rsi,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IStochasticParams {
+    /// Bars of the highest high and lowest low
+    pub k_period: u32,
+    /// SMA applied to the raw %K, 1 for the fast stochastic
+    pub k_smoothing: u32,
+    /// SMA of %K giving %D
+    pub d_period: u32,
+}
+
+impl Default for IStochasticParams {
+    /// Slow stochastic 14 / 3 / 3
+    fn default() -> Self {
+        IStochasticParams {
+            k_period: 14,
+            k_smoothing: 3,
+            d_period: 3,
+        }
+    }
+}
+
+/// %K and %D from 0 to 100, both aligned on %D so the last values belong to the last bar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IStochastic<T> {
+    pub k: Vec<T>,
+    pub d: Vec<T>,
+}
+
+/// SMA of every window of `period` values
+fn smooth<T: Add<Output = T> + Div<Output = T> + Copy>(
+    values: &[T],
+    period: usize,
+    zero: T,
+    length: T,
+) -> Vec<T> {
+    values
+        .windows(period)
+        .map(|window| window.iter().fold(zero, |sum, value| sum + *value) / length)
+        .collect()
+}
+
+fn calculate_stochastic<
+    T: FromPrimitive
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + PartialOrd
+        + Copy,
+>(
+    highs: &[T],
+    lows: &[T],
+    closes: &[T],
+    params: &IStochasticParams,
+) -> Result<IStochastic<T>, MidasError> {
+    if highs.len() != closes.len() || lows.len() != closes.len() {
+        return Err(MidasError::InvalidInput(format!(
+            "{} highs, {} lows and {} closes",
+            highs.len(),
+            lows.len(),
+            closes.len()
+        )));
+    }
+    let k_period = check_period(closes.len(), params.k_period)?;
+    let k_smoothing = check_period(closes.len() + 1 - k_period, params.k_smoothing)?;
+    let d_period = check_period(closes.len() + 2 - k_period - k_smoothing, params.d_period)?;
+    let zero = from_u32::<T>(0)?;
+    let fifty = from_u32::<T>(50)?;
+    let hundred = from_u32::<T>(100)?;
+
+    // * Flat windows have no range, %K sits in the middle
+    let raw: Vec<T> = (k_period - 1..closes.len())
+        .map(|index| {
+            let start = index + 1 - k_period;
+            let (highest, lowest) =
+                (start..=index).fold((highs[start], lows[start]), |(h, l), i| {
+                    (
+                        if highs[i] > h { highs[i] } else { h },
+                        if lows[i] < l { lows[i] } else { l },
+                    )
+                });
+            if highest > lowest {
+                hundred * (closes[index] - lowest) / (highest - lowest)
+            } else {
+                fifty
+            }
+        })
+        .collect();
+    let k = smooth(&raw, k_smoothing, zero, from_u32(params.k_smoothing)?);
+    let d = smooth(&k, d_period, zero, from_u32(params.d_period)?);
+
+    Ok(IStochastic {
+        k: k[k.len() - d.len()..].to_vec(),
+        d,
+    })
+}
+
+/// Stochastic oscillator of OHLC bars
+pub fn get_stochastic<
+    'a,
+    T: 'a
+        + FromPrimitive
+        + Sum<&'a T>
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + PartialOrd
+        + Copy
+        + Debug,
+>(
+    highs: &'a [T],
+    lows: &'a [T],
+    closes: &'a [T],
+    params: &IStochasticParams,
+) -> Result<IStochastic<T>, MidasError> {
+    calculate_stochastic(highs, lows, closes, params)
+}
+
+/// Stochastic oscillator applied to Wilder's RSI of `rsi_period`
+pub fn get_stochastic_rsi<
+    'a,
+    T: 'a
+        + FromPrimitive
+        + Sum<&'a T>
+        + Add<Output = T>
+        + Sub<Output = T>
+        + Mul<Output = T>
+        + Div<Output = T>
+        + PartialOrd
+        + Copy
+        + Debug,
+>(
+    prices: &'a [T],
+    rsi_period: u32,
+    params: &IStochasticParams,
+) -> Result<IStochastic<T>, MidasError> {
+    let rsi = get_rsi(prices, rsi_period)?;
+    calculate_stochastic(&rsi, &rsi, &rsi, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::mock::mock_prices;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_stochastic() {
+        let highs = [dec!(10), dec!(12), dec!(14), dec!(13), dec!(12)];
+        let lows = [dec!(8), dec!(9), dec!(11), dec!(10), dec!(8)];
+        let closes = [dec!(9), dec!(11), dec!(13), dec!(11), dec!(9)];
+        let params = IStochasticParams {
+            k_period: 3,
+            k_smoothing: 1,
+            d_period: 2,
+        };
+
+        let stochastic = get_stochastic(&highs, &lows, &closes, &params).unwrap();
+
+        // * Raw %K: (13 - 8) / (14 - 8), (11 - 9) / (14 - 9), (9 - 8) / (14 - 8)
+        let raw = [dec!(500) / dec!(6), dec!(40), dec!(100) / dec!(6)];
+        assert!(stochastic.k == raw[1..]);
+        assert!(stochastic.d == vec![(raw[0] + raw[1]) / dec!(2), (raw[1] + raw[2]) / dec!(2)]);
+        assert!(get_stochastic(&highs, &lows[1..], &closes, &params).is_err());
+    }
+
+    #[test]
+    fn test_stochastic_rsi() {
+        let prices = mock_prices();
+
+        let stochastic = get_stochastic_rsi(&prices, 5, &IStochasticParams::default()).unwrap();
+
+        // * 25 RSI values, 12 raw %K, 10 smoothed, 8 %D
+        assert!(stochastic.d.len() == 8);
+        assert!(stochastic.k.len() == stochastic.d.len());
+        assert!(stochastic
+            .k
+            .iter()
+            .chain(&stochastic.d)
+            .all(|value| *value >= Decimal::ZERO && *value <= dec!(100)));
+    }
+}
@@ -1,5 +1,16 @@
 use rust_decimal_macros::dec;
 
+use num::ToPrimitive;
+
+use crate::backtest::strategy::{MaCrossStrategy, Signal, Strategy};
+use crate::backtest::{IBackTestingParams, IBackTestingSingleResult, POSITION};
+use crate::error::MidasError;
+use crate::get_price::candle::{Candle, PriceSeries};
+use crate::risk_management::exit_levels::ExitReason;
+use crate::utils::DAY_IN_MILLISECONDS;
+
+const MOCK_START_TIMESTAMP: i64 = 1_640_995_200_000;
+
 pub fn mock_prices() -> [rust_decimal::Decimal; 30] {
     [
         dec!(2),
@@ -34,3 +45,74 @@ pub fn mock_prices() -> [rust_decimal::Decimal; 30] {
         dec!(24.12),
     ]
 }
+
+/// Daily candles built from `mock_prices`, starting at 2022-01-01 UTC
+pub fn mock_candles() -> Vec<Candle> {
+    mock_prices()
+        .iter()
+        .enumerate()
+        .map(|(index, price)| {
+            Candle::from_price(
+                MOCK_START_TIMESTAMP + index as i64 * DAY_IN_MILLISECONDS,
+                *price,
+                dec!(1000),
+            )
+        })
+        .collect::<Vec<Candle>>()
+}
+
+/// Fill of one unit at `price` without any cost, on bar `index`
+pub fn mock_fill(
+    action: POSITION,
+    price: rust_decimal::Decimal,
+    index: usize,
+) -> IBackTestingSingleResult<rust_decimal::Decimal> {
+    IBackTestingSingleResult {
+        action,
+        price,
+        timestamp: index as i64,
+        index,
+        fill_price: price,
+        quantity: dec!(1),
+        fee: dec!(0),
+        exit_reason: match action {
+            POSITION::Close | POSITION::Cover => Some(ExitReason::Signal),
+            POSITION::Long | POSITION::Short => None,
+        },
+    }
+}
+
+/// Strategy builder for `grid_search` and `walk_forward`, parameters are the fast and slow periods
+pub fn mock_ma_cross(
+    parameters: &[rust_decimal::Decimal],
+    _: &mut IBackTestingParams,
+) -> Result<Box<dyn Strategy>, MidasError> {
+    Ok(Box::new(MaCrossStrategy {
+        fast: parameters[0].to_u32().unwrap_or_default(),
+        slow: parameters[1].to_u32().unwrap_or_default(),
+    }))
+}
+
+/// Long on the bar before the highest close, peeking at the whole series
+pub struct MockHindsight;
+
+impl Strategy for MockHindsight {
+    fn name(&self) -> String {
+        "hindsight".to_string()
+    }
+    fn next(&mut self, bars: &[Candle]) -> Result<Option<Signal>, MidasError> {
+        Ok(self
+            .signals(&PriceSeries::new(bars.to_vec()))?
+            .pop()
+            .flatten())
+    }
+    fn signals(&mut self, series: &PriceSeries) -> Result<Vec<Option<Signal>>, MidasError> {
+        let closes = series.closes();
+        let mut signals: Vec<Option<Signal>> = vec![None; closes.len()];
+        let top = (0..closes.len()).max_by_key(|index| closes[*index]);
+        if let Some(top) = top.filter(|top| *top > 0) {
+            signals[top - 1] = Some(Signal::Long);
+        }
+        Ok(signals)
+    }
+}
@@ -4,8 +4,13 @@ use std::hash::Hash;
 use std::iter::Sum;
 use std::ops::{Add, Div, Sub};
 pub mod mock;
+pub mod random;
 
-use num::FromPrimitive;
+use num::{CheckedDiv, FromPrimitive};
+
+use crate::error::MidasError;
+
+pub const DAY_IN_MILLISECONDS: i64 = 86_400_000;
 
 pub fn remove_same_values_in_slice<T: Clone + Eq + Hash>(v1: &[T], v2: &[T]) -> Box<[T]> {
     let hs1: HashSet<T> = v1.iter().cloned().collect();
@@ -59,16 +64,25 @@ pub fn median<T: Ord + Copy + Add<Output = T> + Div<Output = T> + FromPrimitive
     }
 }
 
-pub fn calculate_maximum_drawdown<T: Ord + Copy + Sub<Output = T> + Div<Output = T>>(values: Vec<T>) -> T {
+/// Deepest fall from a running peak, e.g. `-0.5` for 200 -> 100, `None` when there are less than two values
+/// A zero peak is an `ArithmeticOverflow`
+pub fn calculate_maximum_drawdown<T: Ord + Copy + Sub<Output = T> + CheckedDiv>(
+    values: Vec<T>,
+) -> Result<Option<T>, MidasError> {
+    if values.len() < 2 {
+        return Ok(None);
+    }
+    let mut peak: T = values[0];
     let mut drawdown: Vec<T> = Vec::new();
 
-    for index in 0..values.len() {
-        if index != 0 {
-            drawdown.push((values[index] - values[index - 1]) / values[index - 1]);
-        }
+    for (index, value) in values.into_iter().enumerate() {
+        peak = peak.max(value);
+        drawdown.push((value - peak).checked_div(&peak).ok_or_else(|| {
+            MidasError::ArithmeticOverflow(format!("drawdown from a zero peak at {index}"))
+        })?);
     }
 
-    *drawdown.iter().min().expect("Failed to calculate maximum drawdown")
+    Ok(drawdown.iter().min().copied())
 }
 
 #[cfg(test)]
@@ -96,5 +110,19 @@ mod tests {
 
         assert!(median(&mut slices_of_dec) == dec!(25));
     }
-}
 
+    #[test]
+    fn test_calculate_maximum_drawdown() {
+        // * Two small steps down add up to a 30% fall from the peak
+        let values = vec![dec!(100), dec!(120), dec!(96), dec!(84), dec!(130)];
+
+        assert!(calculate_maximum_drawdown(values).unwrap() == Some(dec!(-0.3)));
+        assert!(calculate_maximum_drawdown(vec![dec!(100)])
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            calculate_maximum_drawdown(vec![dec!(0), dec!(0)]),
+            Err(MidasError::ArithmeticOverflow(_))
+        ));
+    }
+}
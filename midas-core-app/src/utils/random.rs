@@ -0,0 +1,63 @@
+/// Small seeded generator ([SplitMix64](https://prng.di.unimi.it/splitmix64.c)), the same seed
+/// always gives the same sequence so simulations can be reproduced
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> SeededRng {
+        SeededRng { state: seed }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^ (value >> 31)
+    }
+    /// Uniform in `0..bound`, `bound` must not be zero
+    pub fn next_below(&mut self, bound: usize) -> usize {
+        // * Reject the top of the range so every value is equally likely
+        let bound = bound as u64;
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return (value % bound) as usize;
+            }
+        }
+    }
+    /// Fisher-Yates shuffle
+    pub fn shuffle<T>(&mut self, values: &mut [T]) {
+        for index in (1..values.len()).rev() {
+            let other = self.next_below(index + 1);
+            values.swap(index, other);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_rng() {
+        let mut rng = SeededRng::new(42);
+        let mut same = SeededRng::new(42);
+        let mut other = SeededRng::new(7);
+        let sequence: Vec<u64> = (0..5).map(|_| rng.next_u64()).collect();
+
+        assert!(sequence == (0..5).map(|_| same.next_u64()).collect::<Vec<u64>>());
+        let other: Vec<u64> = (0..5).map(|_| other.next_u64()).collect();
+        assert!(sequence != other);
+        // * Every draw moves the generator forward
+        assert!(other.windows(2).all(|pair| pair[0] != pair[1]));
+        assert!((0..1000).all(|_| rng.next_below(6) < 6));
+
+        let mut values: Vec<usize> = (0..10).collect();
+        rng.shuffle(&mut values);
+        values.sort();
+        assert!(values == (0..10).collect::<Vec<usize>>());
+    }
+}